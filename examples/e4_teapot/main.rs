@@ -2,9 +2,11 @@ use std::{cell::RefCell, rc::Rc};
 
 use glam::Vec3;
 use glium::{uniform, Surface};
-use opengl_examples_rs::{camera::Camera, helper};
-
-mod model;
+use opengl_examples_rs::{
+    bounds::BoundingSphere,
+    camera::{Camera, CameraMode},
+    helper, mesh, primitives, upload,
+};
 
 fn main() {
     let event_loop = winit::event_loop::EventLoopBuilder::new()
@@ -15,14 +17,9 @@ fn main() {
         .with_inner_size(1600, 1200)
         .build(&event_loop);
 
-    let positions = glium::VertexBuffer::new(&display, &model::VERTICES).unwrap();
-    let normals = glium::VertexBuffer::new(&display, &model::NORMALS).unwrap();
-    let indices = glium::IndexBuffer::new(
-        &display,
-        glium::index::PrimitiveType::TrianglesList,
-        &model::INDICES,
-    )
-    .unwrap();
+    let mut teapot = primitives::teapot(2., 16);
+    mesh::optimize_vertex_cache(&mut teapot.indices, teapot.vertices.len());
+    let buffers = upload::upload_mesh(&display, &teapot, &Default::default()).unwrap();
 
     let program = helper::gl_draw_program(
         &display,
@@ -41,11 +38,14 @@ fn main() {
         ..Default::default()
     };
 
-    let position = 250. * Vec3::Z - 50. * Vec3::Y;
+    let bounds = BoundingSphere::from_points(teapot.vertices.iter().map(|v| v.position.into()));
+    let position = 5. * Vec3::Z - Vec3::Y;
     let camera = Rc::new(RefCell::new(
         Camera::new()
             .with_position(position)
-            .with_up_lookat(position.cross(Vec3::X), Vec3::ZERO),
+            .with_up_lookat(position.cross(Vec3::X), Vec3::ZERO)
+            .with_framing(&bounds)
+            .with_mode(CameraMode::Orbit),
     ));
 
     let fn_on_redraw = || {
@@ -57,19 +57,21 @@ fn main() {
         let light = [-1.0, 0.4, 0.9f32];
 
         camera.borrow_mut().update();
-        target
-            .draw(
-                (&positions, &normals),
-                &indices,
-                &program,
-                &uniform! {
-                    view: camera.borrow().view_matrix().to_cols_array_2d(),
-                    perspective: camera.borrow().perspective_matrix().to_cols_array_2d(),
-                    u_light: light
-                },
-                &params,
-            )
-            .unwrap();
+        for part in &buffers {
+            target
+                .draw(
+                    &part.vertices,
+                    &part.indices,
+                    &program,
+                    &uniform! {
+                        view: camera.borrow().view_matrix().to_cols_array_2d(),
+                        perspective: camera.borrow().perspective_matrix().to_cols_array_2d(),
+                        u_light: light
+                    },
+                    &params,
+                )
+                .unwrap();
+        }
         target.finish().unwrap();
     };
 
@@ -1,10 +1,55 @@
 use std::{cell::RefCell, rc::Rc};
 
 use glam::Vec3;
-use glium::{uniform, Surface};
-use opengl_examples_rs::{camera::Camera, helper, obj_loader};
+use glium::{uniform, Rect, Surface};
+use opengl_examples_rs::{
+    asset_loader::{AssetError, AssetEvent, AssetLoader},
+    camera::Camera,
+    helper,
+    mesh::Model,
+    obj_loader::ObjLoadError,
+    upload::{self, MeshBuffers},
+};
+
+/// Optimize a freshly loaded model, on the loader thread.
+fn optimize_model(model: &mut Model) {
+    let before = model.mesh.cache_stats(16);
+    model.optimize(&Default::default());
+    let after = model.mesh.cache_stats(16);
+    log::info!("ACMR {:.3} -> {:.3}", before.acmr, after.acmr);
+}
+
+/// The loaded model, or the placeholder for Git LFS pointers.
+fn loaded_model(result: Result<Model, AssetError>) -> Model {
+    match result {
+        Ok(model) => model,
+        Err(e @ AssetError::Obj(ObjLoadError::LfsPointer { .. })) => {
+            log::warn!("{}, using a placeholder mesh", e);
+            Model::placeholder()
+        }
+        Err(e) => panic!("Failed to load the model: {}", e),
+    }
+}
+
+/// Grey bar across the middle of the frame, filled up to `progress`.
+fn draw_loading_bar<S: Surface>(target: &mut S, progress: f32) {
+    let (width, height) = target.get_dimensions();
+    let bar = Rect {
+        left: width / 4,
+        bottom: height / 2 - 10,
+        width: width / 2,
+        height: 20,
+    };
+    target.clear(Some(&bar), Some((0.2, 0.2, 0.2, 1.)), false, None, None);
+    let filled = Rect {
+        width: (bar.width as f32 * progress.clamp(0., 1.)) as u32,
+        ..bar
+    };
+    target.clear(Some(&filled), Some((0.8, 0.8, 0.8, 1.)), false, None, None);
+}
 
 fn main() {
+    helper::init_logger();
     let event_loop = winit::event_loop::EventLoopBuilder::new()
         .build()
         .expect("event loop building");
@@ -13,15 +58,14 @@ fn main() {
         .with_inner_size(1600, 1200)
         .build(&event_loop);
 
-    let (vertices, indices) = obj_loader::load_obj("examples/model/AlienAnimal.obj");
-
-    let vb = glium::VertexBuffer::new(&display, &vertices).unwrap();
-    let ib = glium::IndexBuffer::new(
-        &display,
-        glium::index::PrimitiveType::TrianglesList,
-        &indices,
-    )
-    .unwrap();
+    // the model given on the command line, or the alien by default
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "examples/model/AlienAnimal.obj".to_owned());
+    let mut loader = AssetLoader::with_post_process(1, optimize_model);
+    loader.load(path);
+    let mut progress = 0.;
+    let mut scene: Option<(Model, Vec<MeshBuffers>)> = None;
 
     let program = helper::gl_draw_program(
         &display,
@@ -36,7 +80,7 @@ fn main() {
             write: true,
             ..Default::default()
         },
-        backface_culling: glium::draw_parameters::BackfaceCullingMode::CullCounterClockwise,
+        backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
         ..Default::default()
     };
 
@@ -45,33 +89,63 @@ fn main() {
         Camera::new()
             .with_position(position)
             .with_up_lookat(position.cross(Vec3::X), Vec3::ZERO)
-            .with_move_sensitivity(2.)
-            .with_rotate_sensitivity(0.05),
+            .with_move_sensitivity(60.)
+            .with_rotate_sensitivity(1.),
     ));
 
     let fn_on_redraw = || {
+        for event in loader.poll() {
+            match event {
+                AssetEvent::Progress { fraction, .. } => progress = fraction,
+                AssetEvent::Loaded { result, .. } => {
+                    let model = loaded_model(result);
+                    camera.borrow_mut().frame(&model.mesh.bounding_sphere());
+                    let buffers =
+                        upload::upload_model(&display, &model, &Default::default()).unwrap();
+                    scene = Some((model, buffers));
+                }
+            }
+        }
+
         let mut target = display.draw();
 
         target.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
 
+        let Some((model, buffers)) = &scene else {
+            draw_loading_bar(&mut target, progress);
+            target.finish().unwrap();
+            return;
+        };
+
         // the direction of the light
         let light = [-1.0, 0.4, 0.9f32];
 
         camera.borrow_mut().update();
-        target
-            .draw(
-                &vb,
-                &ib,
-                &program,
-                &uniform! {
-                    eye_position: camera.borrow().position().to_array(),
-                    view: camera.borrow().view_matrix().to_cols_array_2d(),
-                    perspective: camera.borrow().perspective_matrix().to_cols_array_2d(),
-                    u_light: light
-                },
-                &params,
-            )
-            .unwrap();
+        for (part, (submesh, indices)) in buffers
+            .iter()
+            .flat_map(|b| b.submeshes.iter().map(move |s| (b, s)))
+        {
+            let diffuse = model
+                .material(&model.submeshes[*submesh])
+                .cloned()
+                .unwrap_or_default()
+                .diffuse;
+            target
+                .draw(
+                    &part.vertices,
+                    part.indices.slice(indices.clone()).unwrap(),
+                    &program,
+                    &uniform! {
+                        eye_position: camera.borrow().position().to_array(),
+                        view: camera.borrow().view_matrix().to_cols_array_2d(),
+                        perspective: camera.borrow().perspective_matrix().to_cols_array_2d(),
+                        u_light: light,
+                        u_diffuse: diffuse
+                    },
+                    &params,
+                )
+                .unwrap();
+        }
         target.finish().unwrap();
     };
 
@@ -2,13 +2,15 @@
 
 in vec3 v_normal;
 in vec3 v_eye_to_light;
+in vec3 v_color;
 
 out vec4 color;
 
 uniform vec3 u_light;
+uniform vec3 u_diffuse;
 
 void main() {
-    vec3 c_surface = vec3(0.8, 0.7, 0.6);
+    vec3 c_surface = u_diffuse * v_color;
     vec3 c_cool = vec3(0.0, 0.0, 0.55) + 0.25 * c_surface;
     vec3 c_warm = vec3(0.3, 0.3, 0.0) + 0.25 * c_surface;
     vec3 c_highlight = vec3(1.0, 1.0, 1.0);
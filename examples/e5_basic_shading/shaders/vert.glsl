@@ -2,9 +2,11 @@
 
 in vec3 position;
 in vec3 normal;
+in vec4 color;
 
 out vec3 v_normal;
 out vec3 v_eye_to_light;
+out vec3 v_color;
 
 uniform vec3 eye_position;
 uniform mat4 view;
@@ -14,5 +16,6 @@ uniform mat4 perspective;
 void main() {
     v_normal = transpose(inverse(mat3(view))) * normal;
     v_eye_to_light = normalize(position - eye_position);
+    v_color = color.rgb;
     gl_Position = perspective * view * vec4(position, 1.0);
 }
\ No newline at end of file
@@ -0,0 +1,425 @@
+use std::{
+    any::Any,
+    error::Error,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    gltf_loader::{self, GltfLoadError},
+    mesh::Model,
+    obj_loader::{self, ObjLoadError, ObjLoadOptions},
+    ply_loader::{self, PlyLoadError},
+    stl_loader::{self, StlLoadError},
+};
+
+#[derive(Debug)]
+pub enum AssetError {
+    Obj(ObjLoadError),
+    Gltf(GltfLoadError),
+    Ply(PlyLoadError),
+    Stl(StlLoadError),
+    /// The file extension is not one of the supported formats.
+    UnknownFormat {
+        path: PathBuf,
+    },
+    /// The loader panicked on the file.
+    Panicked {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Obj(e) => e.fmt(f),
+            AssetError::Gltf(e) => e.fmt(f),
+            AssetError::Ply(e) => e.fmt(f),
+            AssetError::Stl(e) => e.fmt(f),
+            AssetError::UnknownFormat { path } => {
+                write!(f, "unknown model format for {}", path.display())
+            }
+            AssetError::Panicked { path, message } => {
+                write!(f, "loading {} panicked: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl Error for AssetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AssetError::Obj(e) => Some(e),
+            AssetError::Gltf(e) => Some(e),
+            AssetError::Ply(e) => Some(e),
+            AssetError::Stl(e) => Some(e),
+            AssetError::UnknownFormat { .. } | AssetError::Panicked { .. } => None,
+        }
+    }
+}
+
+/// Load a model in any supported format, picked from the file extension.
+pub fn load_model<P>(path: P) -> Result<Model, AssetError>
+where
+    P: AsRef<Path>,
+{
+    load_model_with_progress(path.as_ref(), |_| {})
+}
+
+/// [`load_model`] reporting the fraction parsed so far. Only OBJ files report
+/// intermediate values, the other formats go from 0 to 1.
+pub fn load_model_with_progress<F>(path: &Path, mut progress: F) -> Result<Model, AssetError>
+where
+    F: FnMut(f32),
+{
+    let mut reported = -1.;
+    let mut progress = |fraction: f32| {
+        if fraction > reported {
+            reported = fraction;
+            progress(fraction);
+        }
+    };
+    progress(0.);
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    let model = match extension.as_deref() {
+        Some("obj") => obj_loader::try_load_model_with_progress(
+            path,
+            &ObjLoadOptions::default(),
+            &mut progress,
+        )
+        .map_err(AssetError::Obj)?,
+        Some("gltf" | "glb") => gltf_loader::try_load_gltf(path).map_err(AssetError::Gltf)?,
+        Some("ply") => ply_loader::try_load_ply(path)
+            .map(Model::from)
+            .map_err(AssetError::Ply)?,
+        Some("stl") => stl_loader::try_load_stl(path)
+            .map(Model::from)
+            .map_err(AssetError::Stl)?,
+        _ => {
+            return Err(AssetError::UnknownFormat {
+                path: path.to_owned(),
+            })
+        }
+    };
+    progress(1.);
+    Ok(model)
+}
+
+/// Identifies a request made to an [`AssetLoader`].
+pub type AssetId = u64;
+
+#[derive(Debug)]
+pub enum AssetEvent {
+    /// `fraction` of the asset has been loaded, from 0 to 1.
+    Progress { id: AssetId, fraction: f32 },
+    /// The asset finished loading, successfully or not.
+    Loaded {
+        id: AssetId,
+        path: PathBuf,
+        result: Result<Model, AssetError>,
+    },
+}
+
+struct Job {
+    id: AssetId,
+    path: PathBuf,
+}
+
+/// Loads models on worker threads. The render thread queues files with
+/// [`AssetLoader::load`] and picks up progress and finished CPU models with
+/// [`AssetLoader::poll`] between frames, uploading them itself since GL
+/// objects must be created on its context. Other processing of the models
+/// belongs in a [post-process](AssetLoader::with_post_process) run by the
+/// workers.
+///
+/// Dropping the loader does not wait for the workers, they finish the asset
+/// they are on and exit.
+pub struct AssetLoader {
+    jobs: Sender<Job>,
+    events: Receiver<AssetEvent>,
+    next_id: AssetId,
+    pending: usize,
+}
+
+impl AssetLoader {
+    /// Start `workers` threads, at least one.
+    pub fn new(workers: usize) -> Self {
+        AssetLoader::with_load(workers, default_load, None)
+    }
+
+    /// [`AssetLoader::new`] with the workers running `post_process` on every
+    /// model they load, so that slow steps such as [`Model::optimize`] do not
+    /// stall the render thread.
+    pub fn with_post_process<F>(workers: usize, post_process: F) -> Self
+    where
+        F: Fn(&mut Model) + Send + Sync + 'static,
+    {
+        AssetLoader::with_load(workers, default_load, Some(Arc::new(post_process)))
+    }
+
+    fn with_load(workers: usize, load: Load, post_process: Option<PostProcess>) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (event_sender, events) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        for i in 0..workers.max(1) {
+            let jobs = Arc::clone(&job_receiver);
+            let events = event_sender.clone();
+            let post_process = post_process.clone();
+            thread::Builder::new()
+                .name(format!("asset-loader-{}", i))
+                .spawn(move || worker(&jobs, &events, load, post_process.as_deref()))
+                .expect("Failed to spawn asset loader thread");
+        }
+        AssetLoader {
+            jobs,
+            events,
+            next_id: 0,
+            pending: 0,
+        }
+    }
+
+    /// Queue `path` for loading, see [`load_model`] for the formats.
+    pub fn load<P>(&mut self, path: P) -> AssetId
+    where
+        P: AsRef<Path>,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending += 1;
+        let job = Job {
+            id,
+            path: path.as_ref().to_owned(),
+        };
+        // the workers only stop once `jobs` is dropped
+        self.jobs.send(job).unwrap();
+        id
+    }
+
+    /// Number of queued assets that have not been returned as loaded yet.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Events received since the last call, without blocking.
+    pub fn poll(&mut self) -> Vec<AssetEvent> {
+        let events = self.events.try_iter().collect::<Vec<_>>();
+        self.count_loaded(&events);
+        events
+    }
+
+    /// Block until the next event, `None` once nothing is pending.
+    pub fn wait(&mut self) -> Option<AssetEvent> {
+        if self.pending == 0 {
+            return None;
+        }
+        let event = self.events.recv().ok()?;
+        self.count_loaded(std::slice::from_ref(&event));
+        Some(event)
+    }
+
+    fn count_loaded(&mut self, events: &[AssetEvent]) {
+        self.pending -= events
+            .iter()
+            .filter(|e| matches!(e, AssetEvent::Loaded { .. }))
+            .count();
+    }
+}
+
+impl Default for AssetLoader {
+    /// One worker per available core, keeping one for the render thread.
+    fn default() -> Self {
+        let cores = thread::available_parallelism().map_or(2, |n| n.get());
+        AssetLoader::new(cores - 1)
+    }
+}
+
+/// How workers load a file, [`load_model_with_progress`] outside of tests.
+type Load = fn(&Path, &mut dyn FnMut(f32)) -> Result<Model, AssetError>;
+
+/// Shared by the workers to process the models they load.
+type PostProcess = Arc<dyn Fn(&mut Model) + Send + Sync>;
+
+fn default_load(path: &Path, progress: &mut dyn FnMut(f32)) -> Result<Model, AssetError> {
+    load_model_with_progress(path, progress)
+}
+
+fn worker(
+    jobs: &Mutex<Receiver<Job>>,
+    events: &Sender<AssetEvent>,
+    load: Load,
+    post_process: Option<&(dyn Fn(&mut Model) + Send + Sync)>,
+) {
+    loop {
+        // the lock is released before loading so other workers can pick jobs
+        let job = jobs.lock().unwrap().recv();
+        let Ok(Job { id, path }) = job else {
+            return;
+        };
+        let mut progress = |fraction| {
+            let _ = events.send(AssetEvent::Progress { id, fraction });
+        };
+        // a panic must still end with a `Loaded` event, or `wait` would block
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut model = load(&path, &mut progress)?;
+            if let Some(post_process) = post_process {
+                post_process(&mut model);
+            }
+            Ok(model)
+        }))
+        .unwrap_or_else(|payload| {
+            Err(AssetError::Panicked {
+                path: path.clone(),
+                message: panic_message(payload),
+            })
+        });
+        // the loader was dropped, nobody is listening anymore
+        if events
+            .send(AssetEvent::Loaded { id, path, result })
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map_or_else(|| "unknown panic".to_owned(), |&m| m.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::{collections::HashMap, fmt::Write};
+
+    #[test]
+    fn models_load_in_the_background() {
+        let dir = TempDir::new("asset_loader");
+        // a grid big enough for several progress reports
+        let mut src = String::new();
+        for y in 0..50 {
+            for x in 0..50 {
+                writeln!(src, "v {} {} 0", x, y).unwrap();
+            }
+        }
+        for y in 0..49 {
+            for x in 0..49 {
+                let i = y * 50 + x + 1;
+                writeln!(
+                    src,
+                    "f {} {} {}\nf {} {} {}",
+                    i,
+                    i + 1,
+                    i + 51,
+                    i,
+                    i + 51,
+                    i + 50
+                )
+                .unwrap();
+            }
+        }
+        std::fs::write(dir.join("grid.obj"), src).unwrap();
+
+        let mut loader = AssetLoader::new(2);
+        let grid = loader.load(dir.join("grid.obj"));
+        let missing = loader.load(dir.join("missing.stl"));
+        let unknown = loader.load(dir.join("grid.txt"));
+        assert_eq!(loader.pending(), 3);
+
+        let mut progress = HashMap::<AssetId, Vec<f32>>::new();
+        let mut results = HashMap::new();
+        while let Some(event) = loader.wait() {
+            match event {
+                AssetEvent::Progress { id, fraction } => {
+                    progress.entry(id).or_default().push(fraction)
+                }
+                AssetEvent::Loaded { id, result, .. } => {
+                    results.insert(id, result);
+                }
+            }
+        }
+        assert_eq!(loader.pending(), 0);
+
+        assert_eq!(
+            results[&grid].as_ref().unwrap().mesh.indices.len(),
+            49 * 49 * 6
+        );
+        assert!(matches!(
+            results[&missing],
+            Err(AssetError::Stl(StlLoadError::Io { .. }))
+        ));
+        assert!(matches!(
+            results[&unknown],
+            Err(AssetError::UnknownFormat { .. })
+        ));
+
+        let grid_progress = &progress[&grid];
+        assert!(grid_progress.len() > 10);
+        assert!(grid_progress.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(grid_progress.first(), Some(&0.));
+        assert_eq!(grid_progress.last(), Some(&1.));
+    }
+
+    #[test]
+    fn panicking_loads_are_reported() {
+        let mut loader = AssetLoader::with_load(
+            1,
+            |path, _| {
+                if path.ends_with("bad.obj") {
+                    panic!("bad file");
+                }
+                Ok(Model::placeholder())
+            },
+            None,
+        );
+        let bad = loader.load("bad.obj");
+        let good = loader.load("good.obj");
+        let mut results = HashMap::new();
+        while let Some(event) = loader.wait() {
+            if let AssetEvent::Loaded { id, result, .. } = event {
+                results.insert(id, result);
+            }
+        }
+        match &results[&bad] {
+            Err(AssetError::Panicked { message, .. }) => assert_eq!(message, "bad file"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        // the worker survives
+        assert!(results[&good].is_ok());
+    }
+
+    #[test]
+    fn post_processing_runs_on_the_workers() {
+        let mut loader = AssetLoader::with_load(
+            1,
+            |_, _| Ok(Model::placeholder()),
+            Some(Arc::new(|model: &mut Model| {
+                model.submeshes[0].name = thread::current().name().unwrap().to_owned();
+            })),
+        );
+        loader.load("cube.obj");
+        let model = loop {
+            match loader.wait() {
+                Some(AssetEvent::Loaded { result, .. }) => break result.unwrap(),
+                Some(_) => {}
+                None => panic!("no model loaded"),
+            }
+        };
+        assert_eq!(model.submeshes[0].name, "asset-loader-0");
+    }
+}
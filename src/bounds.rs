@@ -0,0 +1,155 @@
+use glam::Vec3;
+
+use crate::mesh::Mesh;
+
+/// Axis-aligned bounding box, empty when `min > max` on any axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl Aabb {
+    pub const EMPTY: Aabb = Aabb {
+        min: Vec3::INFINITY,
+        max: Vec3::NEG_INFINITY,
+    };
+
+    pub fn from_points<I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = Vec3>,
+    {
+        points
+            .into_iter()
+            .fold(Self::EMPTY, |aabb, p| aabb.extend(p))
+    }
+
+    pub fn extend(self, p: Vec3) -> Self {
+        Aabb {
+            min: self.min.min(p),
+            max: self.max.max(p),
+        }
+    }
+
+    pub fn union(self, other: Aabb) -> Self {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) / 2.
+    }
+
+    pub fn size(&self) -> Vec3 {
+        (self.max - self.min).max(Vec3::ZERO)
+    }
+
+    pub fn contains(&self, p: Vec3) -> bool {
+        p.cmpge(self.min).all() && p.cmple(self.max).all()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Ritter's approximation, at most a few percent larger than the minimal
+    /// sphere. Points must be iterable twice.
+    pub fn from_points<I>(points: I) -> Self
+    where
+        I: IntoIterator<Item = Vec3> + Clone,
+    {
+        let Some(first) = points.clone().into_iter().next() else {
+            return Self::default();
+        };
+        let farthest = |from: Vec3| {
+            points
+                .clone()
+                .into_iter()
+                .max_by(|a, b| {
+                    a.distance_squared(from)
+                        .total_cmp(&b.distance_squared(from))
+                })
+                .unwrap_or(from)
+        };
+        let a = farthest(first);
+        let b = farthest(a);
+        let mut sphere = BoundingSphere {
+            center: (a + b) / 2.,
+            radius: a.distance(b) / 2.,
+        };
+        for p in points {
+            let d = p.distance(sphere.center);
+            if d > sphere.radius {
+                let radius = (sphere.radius + d) / 2.;
+                sphere.center += (p - sphere.center) * ((radius - sphere.radius) / d);
+                sphere.radius = radius;
+            }
+        }
+        sphere
+    }
+
+    pub fn contains(&self, p: Vec3) -> bool {
+        p.distance(self.center) <= self.radius * (1. + 1e-5)
+    }
+}
+
+impl Mesh {
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_points(self.vertices.iter().map(|v| Vec3::from(v.position)))
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::from_points(self.vertices.iter().map(|v| Vec3::from(v.position)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholder_bounds() {
+        let mesh = Mesh::placeholder();
+        let aabb = mesh.aabb();
+        assert_eq!(aabb.min, -Vec3::ONE);
+        assert_eq!(aabb.max, Vec3::ONE);
+        assert!(Aabb::EMPTY.is_empty() && !aabb.is_empty());
+
+        let sphere = mesh.bounding_sphere();
+        assert!(sphere.center.abs_diff_eq(Vec3::ZERO, 1e-6));
+        assert!((sphere.radius - 3_f32.sqrt()).abs() < 1e-5);
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|v| sphere.contains(Vec3::from(v.position))));
+    }
+
+    #[test]
+    fn sphere_grows_to_cover_outliers() {
+        let points = [
+            Vec3::ZERO,
+            Vec3::X,
+            Vec3::new(0.5, 3., 0.),
+            Vec3::new(-2., -1., 4.),
+        ];
+        let sphere = BoundingSphere::from_points(points);
+        assert!(points.iter().all(|&p| sphere.contains(p)));
+        assert_eq!(BoundingSphere::from_points([]), BoundingSphere::default());
+    }
+}
@@ -1,38 +1,227 @@
-use std::ops::Range;
+//! Cameras follow the OpenGL conventions: the world is right-handed with Y
+//! up, and in view space the camera sits at the origin looking down -Z with
+//! +X to the right and +Y up. Projections map the clip range to depths -1 to
+//! 1, so counter-clockwise triangles face the viewer.
 
-use glam::{EulerRot, Mat4, Quat, Vec3};
+use std::{
+    f32::consts::{PI, TAU},
+    ops::{Range, RangeInclusive},
+};
+
+use glam::{EulerRot, Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
 use winit::{
-    event::KeyEvent,
-    keyboard::{KeyCode, PhysicalKey},
+    dpi::PhysicalPosition,
+    event::{KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::PhysicalKey,
+};
+
+use crate::{
+    bounds::BoundingSphere,
+    input::InputState,
+    key_bindings::{Action, KeyBindings},
 };
 
+/// Aspect ratios are clamped to this range so extreme window sizes do not
+/// produce degenerate projections.
+pub const ASPECT_RATIO_RANGE: RangeInclusive<f32> = 0.1..=10.;
+
+/// Fraction of the orbit distance panned or zoomed per second at full speed.
+const ORBIT_SPEED: f32 = 1.;
+/// Natural log of the factor the field of view narrows by per second of
+/// [`Action::ZoomIn`].
+const ZOOM_SPEED: f32 = 1.;
+/// Speed multiplier while [`Action::Fast`] is held.
+const FAST_FACTOR: f32 = 4.;
+/// Seconds of full speed movement a scroll wheel line dollies the free camera.
+const SECONDS_PER_LINE: f32 = 0.1;
+/// Fraction of the orbit distance the target pans per pixel dragged.
+const PAN_PER_PIXEL: f32 = 0.001;
+/// Scroll wheel lines per pixel for touchpads reporting pixels.
+const LINES_PER_PIXEL: f32 = 0.02;
+/// Seconds [`Camera::tick`] takes to blend between the perspective and the
+/// orthographic projections.
+pub const PROJECTION_SWITCH_TIME: f32 = 0.3;
+
+/// How input drives the camera.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Flying freely with [`Camera::moving`] and [`Camera::rotating`].
+    #[default]
+    Free,
+    /// Revolving around [`Orbit::target`].
+    Orbit,
+}
+
+/// What the scroll wheel changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScrollZoom {
+    /// Move the camera forward, or closer to the orbit target.
+    #[default]
+    Dolly,
+    /// Narrow the field of view, keeping the camera in place.
+    Fov,
+}
+
+/// Shape of the view volume.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Projection {
+    /// Symmetric frustum of the camera's field of view and aspect ratio.
+    #[default]
+    Perspective,
+    /// Box `height` world units tall, as wide as the aspect ratio makes it.
+    Orthographic { height: f32 },
+    /// Asymmetric frustum, each side given by the tangent of its angle to the
+    /// line of sight, negative to the left and bottom. The aspect ratio is
+    /// ignored.
+    OffCentre {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+    },
+}
+
+/// Placement of the camera in [`CameraMode::Orbit`], on a sphere around its
+/// target. Angles are in radians.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Orbit {
+    pub target: Vec3,
+    /// Angle around Y, 0 puts the camera on the +Z side of the target.
+    pub yaw: f32,
+    /// Angle above the XZ plane of the target.
+    pub pitch: f32,
+    pub distance: f32,
+    /// `None` lets the camera turn all the way around.
+    pub yaw_limits: Option<Range<f32>>,
+    /// Kept inside ]-90, 90[ degrees so the view never flips.
+    pub pitch_limits: Range<f32>,
+    pub distance_limits: Range<f32>,
+}
+
+impl Default for Orbit {
+    fn default() -> Self {
+        let max_pitch = 89_f32.to_radians();
+        Orbit {
+            target: Vec3::ZERO,
+            yaw: 0.,
+            pitch: 0.,
+            distance: 10.,
+            yaw_limits: None,
+            pitch_limits: -max_pitch..max_pitch,
+            distance_limits: 1e-3..f32::INFINITY,
+        }
+    }
+}
+
+impl Orbit {
+    /// Where the camera sits.
+    pub fn eye(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let direction = Vec3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw);
+        self.target + direction * self.distance
+    }
+
+    fn clamp(&mut self) {
+        let max_pitch = 89.9_f32.to_radians();
+        self.pitch = self
+            .pitch
+            .clamp(self.pitch_limits.start, self.pitch_limits.end)
+            .clamp(-max_pitch, max_pitch);
+        if let Some(limits) = &self.yaw_limits {
+            self.yaw = self.yaw.clamp(limits.start, limits.end);
+        } else {
+            self.yaw = (self.yaw + PI).rem_euclid(TAU) - PI;
+        }
+        self.distance = self
+            .distance
+            .clamp(self.distance_limits.start, self.distance_limits.end);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Home {
+    position: Vec3,
+    orientation: Quat,
+    projection: Projection,
+    orbit: Orbit,
+}
+
 #[derive(Debug, Clone)]
 pub struct Camera {
     position: Vec3,
-    oriention: Quat,
+    /// Rotation from view space to world space.
+    orientation: Quat,
     fov: f32,
+    projection: Projection,
     aspect_ratio: f32,
+    /// Framebuffer size in pixels, zero until known.
+    viewport: (u32, u32),
     clip: Range<f32>,
     is_dirty: bool,
+    /// Speed in world units per second.
     move_sensitivity: f32,
+    /// Turning speed in radians per second.
     rotate_sensitivity: f32,
+    /// Fraction of the full speed gained per second while a key is held.
+    acceleration: f32,
+    /// Rate at which the speed decays once keys are released, per second.
+    damping: f32,
+    input: InputState,
+    key_bindings: KeyBindings,
+    /// View restored by [`Camera::reset`].
+    home: Option<Home>,
+    /// Moving and turning velocities in view space, as fractions of the full
+    /// speeds.
+    velocity: Vec3,
+    angular_velocity: Vec3,
+    /// Radians turned per pixel of mouse movement.
+    mouse_sensitivity: f32,
+    /// Fraction the view zooms by per scroll wheel line.
+    zoom_sensitivity: f32,
+    scroll_zoom: ScrollZoom,
     view_m: Mat4,
     perspective_m: Mat4,
+    /// How far the shown projection is from perspective to orthographic,
+    /// trailing [`Camera::projection`] after a switch.
+    orthographic_blend: f32,
+    mode: CameraMode,
+    orbit: Orbit,
+    /// Mouse button held and last cursor position, for dragging.
+    drag: Option<MouseButton>,
+    cursor: Option<PhysicalPosition<f64>>,
 }
 
 impl Default for Camera {
     fn default() -> Self {
         let mut c = Camera {
-            position: -10.0 * Vec3::Z,
-            oriention: Quat::IDENTITY,
+            position: 10.0 * Vec3::Z,
+            orientation: Quat::IDENTITY,
             fov: 45_f32.to_radians(),
+            projection: Projection::Perspective,
             aspect_ratio: 1.,
+            viewport: (0, 0),
             clip: 0.1..1000.,
             is_dirty: true,
             move_sensitivity: 10.,
-            rotate_sensitivity: 0.1,
+            rotate_sensitivity: 1.5,
+            acceleration: 8.,
+            damping: 10.,
+            input: InputState::default(),
+            key_bindings: KeyBindings::default(),
+            home: None,
+            velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+            mouse_sensitivity: 0.003,
+            zoom_sensitivity: 0.1,
+            scroll_zoom: ScrollZoom::Dolly,
             view_m: Mat4::ZERO,
             perspective_m: Mat4::ZERO,
+            orthographic_blend: 0.,
+            mode: CameraMode::Free,
+            orbit: Orbit::default(),
+            drag: None,
+            cursor: None,
         };
         c.update();
         c
@@ -57,13 +246,25 @@ impl Camera {
         self
     }
 
-    pub fn with_aspect_ratio(mut self, aspect_ratio: f32) -> Self {
-        assert!(aspect_ratio > 0.1 && aspect_ratio < 10.);
-        self.aspect_ratio = aspect_ratio;
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self.orthographic_blend = self.orthographic_target();
         self.is_dirty = true;
         self
     }
 
+    /// Orthographic view `height` world units tall.
+    pub fn with_orthographic(self, height: f32) -> Self {
+        assert!(height > 0.);
+        self.with_projection(Projection::Orthographic { height })
+    }
+
+    /// see [`Camera::set_aspect_ratio`]
+    pub fn with_aspect_ratio(mut self, aspect_ratio: f32) -> Self {
+        self.set_aspect_ratio(aspect_ratio);
+        self
+    }
+
     pub fn with_clip(mut self, clip: Range<f32>) -> Self {
         assert!(clip.start > 1e-6);
         self.clip = clip;
@@ -71,11 +272,15 @@ impl Camera {
         self
     }
 
+    /// Turn the camera towards `look_at`, with `up` pointing up on screen.
     pub fn with_up_lookat(mut self, up: Vec3, look_at: Vec3) -> Self {
         let up = up.normalize();
         let direction = (look_at - self.position).normalize();
-        assert!(up.dot(direction) < 0.01);
-        self.oriention = Quat::from_mat4(&Mat4::look_at_rh(self.position, look_at, up));
+        assert!(
+            up.cross(direction).length_squared() > 1e-6,
+            "up must not be parallel to the view direction"
+        );
+        self.orientation = looking_at(self.position, look_at, up);
         self.is_dirty = true;
         self
     }
@@ -91,32 +296,380 @@ impl Camera {
         self.is_dirty = true;
         self
     }
+
+    /// Fraction of the full speed gained per second while a key is held.
+    pub fn with_acceleration(mut self, acceleration: f32) -> Self {
+        assert!(acceleration > 0.);
+        self.acceleration = acceleration;
+        self
+    }
+
+    /// Rate at which the speed decays once keys are released, per second.
+    pub fn with_damping(mut self, damping: f32) -> Self {
+        assert!(damping >= 0.);
+        self.damping = damping;
+        self
+    }
+
+    pub fn with_key_bindings(mut self, key_bindings: KeyBindings) -> Self {
+        self.key_bindings = key_bindings;
+        self
+    }
+
+    /// Radians turned per pixel, for mouse look and orbit dragging.
+    pub fn with_mouse_sensitivity(mut self, sensitivity: f32) -> Self {
+        self.mouse_sensitivity = sensitivity;
+        self
+    }
+
+    /// Fraction of the distance or field of view a scroll wheel line zooms.
+    pub fn with_zoom_sensitivity(mut self, sensitivity: f32) -> Self {
+        assert!(sensitivity > 0. && sensitivity < 1.);
+        self.zoom_sensitivity = sensitivity;
+        self
+    }
+
+    pub fn with_scroll_zoom(mut self, scroll_zoom: ScrollZoom) -> Self {
+        self.scroll_zoom = scroll_zoom;
+        self
+    }
+
+    /// see [`Camera::set_mode`]
+    pub fn with_mode(mut self, mode: CameraMode) -> Self {
+        self.set_mode(mode);
+        self
+    }
+
+    /// Orbit around `orbit.target`, switching to [`CameraMode::Orbit`]. The
+    /// limits must not be reversed, and the distance must stay positive.
+    pub fn with_orbit(mut self, orbit: Orbit) -> Self {
+        let ordered = |limits: &Range<f32>| limits.start <= limits.end;
+        assert!(ordered(&orbit.pitch_limits), "reversed pitch limits");
+        assert!(
+            orbit.yaw_limits.as_ref().map_or(true, ordered),
+            "reversed yaw limits"
+        );
+        assert!(
+            ordered(&orbit.distance_limits) && orbit.distance_limits.start > 0.,
+            "distance limits must be positive and ordered"
+        );
+        self.orbit = orbit;
+        self.mode = CameraMode::Orbit;
+        self.place_on_orbit();
+        self
+    }
+
+    /// see [`Camera::frame`]
+    pub fn with_framing(mut self, bounds: &BoundingSphere) -> Self {
+        self.frame(bounds);
+        self
+    }
 }
 
 impl Camera {
     pub fn update(&mut self) {
         if self.is_dirty {
-            self.view_m = Mat4::from_rotation_translation(self.oriention, self.position);
-            self.perspective_m =
-                Mat4::perspective_lh(self.fov, self.aspect_ratio, self.clip.start, self.clip.end);
+            self.view_m =
+                Mat4::from_rotation_translation(self.orientation, self.position).inverse();
+            let Range {
+                start: near,
+                end: far,
+            } = self.clip;
+            let blend = self.orthographic_blend;
+            self.perspective_m = match self.projection {
+                // still switching
+                Projection::Perspective | Projection::Orthographic { .. }
+                    if blend != self.orthographic_target() =>
+                {
+                    self.blended_projection(blend * blend * (3. - 2. * blend), near, far)
+                }
+                Projection::Perspective => {
+                    Mat4::perspective_rh_gl(self.fov, self.aspect_ratio, near, far)
+                }
+                Projection::Orthographic { height } => {
+                    let (x, y) = (height * self.aspect_ratio / 2., height / 2.);
+                    Mat4::orthographic_rh_gl(-x, x, -y, y, near, far)
+                }
+                Projection::OffCentre {
+                    left,
+                    right,
+                    bottom,
+                    top,
+                } => {
+                    // glFrustum with its sides at a distance of 1
+                    Mat4::from_cols(
+                        Vec4::new(2. / (right - left), 0., 0., 0.),
+                        Vec4::new(0., 2. / (top - bottom), 0., 0.),
+                        Vec4::new(
+                            (right + left) / (right - left),
+                            (top + bottom) / (top - bottom),
+                            -(far + near) / (far - near),
+                            -1.,
+                        ),
+                        Vec4::new(0., 0., -2. * far * near / (far - near), 0.),
+                    )
+                }
+            };
             self.is_dirty = false;
         }
     }
 
+    /// Projection shown `blend` of the way from perspective to orthographic:
+    /// the eye backs away while the field of view narrows so objects at the
+    /// focus distance keep their size, a dolly zoom ending orthographic.
+    fn blended_projection(&self, blend: f32, near: f32, far: f32) -> Mat4 {
+        let distance = self.focus_distance();
+        let height = self.view_height(distance);
+        if blend >= 1. {
+            let (x, y) = (height * self.aspect_ratio / 2., height / 2.);
+            return Mat4::orthographic_rh_gl(-x, x, -y, y, near, far);
+        }
+        let tan = height / (2. * distance) * (1. - blend);
+        let pull = distance / (1. - blend) - distance;
+        Mat4::perspective_rh_gl(2. * tan.atan(), self.aspect_ratio, near + pull, far + pull)
+            * Mat4::from_translation(Vec3::NEG_Z * pull)
+    }
+
+    fn orthographic_target(&self) -> f32 {
+        match self.projection {
+            Projection::Orthographic { .. } => 1.,
+            _ => 0.,
+        }
+    }
+
     /// moving in the camera coordinate system
     pub fn moving(&mut self, v: Vec3) {
-        let v = self.oriention * v;
+        let v = self.orientation * v;
         self.position += v;
         self.is_dirty = true;
     }
 
     pub fn rotating(&mut self, r: Vec3) {
         let q = Quat::from_euler(EulerRot::XYZ, r.x, r.y, r.z);
-        self.oriention *= q;
-        self.oriention = self.oriention.normalize();
+        self.orientation *= q;
+        self.orientation = self.orientation.normalize();
+        self.is_dirty = true;
+    }
+
+    /// Mouse look: turn the free camera by a mouse movement of `delta`
+    /// pixels, yawing around the world Y axis and pitching around the
+    /// camera's own X axis, short of looking straight up or down so the view
+    /// never flips. Does nothing in [`CameraMode::Orbit`].
+    pub fn look(&mut self, delta: Vec2) {
+        if self.mode != CameraMode::Free {
+            return;
+        }
+        let max_pitch = 89_f32.to_radians();
+        let current = self.forward().y.clamp(-1., 1.).asin();
+        // a view already past the limit, turned there with the keys, may
+        // only come back
+        let pitched = (current - delta.y * self.mouse_sensitivity)
+            .clamp(current.min(-max_pitch), current.max(max_pitch));
+        let yaw = Quat::from_rotation_y(-delta.x * self.mouse_sensitivity);
+        let pitch = Quat::from_rotation_x(pitched - current);
+        self.orientation = (yaw * self.orientation * pitch).normalize();
+        self.is_dirty = true;
+    }
+
+    /// Narrow the field of view by `factor`, within the range accepted by
+    /// [`Camera::with_fov`]. Orthographic and off-centre views are scaled
+    /// instead.
+    pub fn zoom_fov(&mut self, factor: f32) {
+        match &mut self.projection {
+            Projection::Perspective => {
+                self.fov = (self.fov * factor).clamp(1_f32.to_radians(), 89_f32.to_radians())
+            }
+            Projection::Orthographic { height } => *height *= factor,
+            Projection::OffCentre {
+                left,
+                right,
+                bottom,
+                top,
+            } => {
+                for side in [left, right, bottom, top] {
+                    *side *= factor;
+                }
+            }
+        }
+        self.is_dirty = true;
+    }
+
+    /// Width over height of the view, clamped to [`ASPECT_RATIO_RANGE`].
+    /// Values that are not positive numbers are ignored.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        if aspect_ratio > 0. && aspect_ratio.is_finite() {
+            self.aspect_ratio =
+                aspect_ratio.clamp(*ASPECT_RATIO_RANGE.start(), *ASPECT_RATIO_RANGE.end());
+            self.is_dirty = true;
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    /// Follow a framebuffer of `width * height` pixels, updating the aspect
+    /// ratio. The empty size of a minimised window is ignored.
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.viewport = (width, height);
+        self.set_aspect_ratio(width as f32 / height as f32);
+    }
+
+    /// Framebuffer size in pixels given to [`Camera::set_viewport`], zero
+    /// until then.
+    pub fn viewport(&self) -> (u32, u32) {
+        self.viewport
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Height of the view, in world units, at `distance` along the line of
+    /// sight.
+    pub fn view_height(&self, distance: f32) -> f32 {
+        match self.projection {
+            Projection::Perspective => 2. * distance * (self.fov / 2.).tan(),
+            Projection::Orthographic { height } => height,
+            Projection::OffCentre { bottom, top, .. } => distance * (top - bottom),
+        }
+    }
+
+    /// Distance along the line of sight to what the view is on: the orbit
+    /// distance in orbit mode, the depth of the orbit target for the free
+    /// camera, which may have moved since, unless the target is behind it.
+    fn focus_distance(&self) -> f32 {
+        match self.mode {
+            CameraMode::Orbit => self.orbit.distance,
+            CameraMode::Free => {
+                let depth = (self.orbit.target - self.position).dot(self.forward());
+                if depth > self.clip.start {
+                    depth
+                } else {
+                    self.orbit.distance
+                }
+            }
+        }
+    }
+
+    /// Switch to an orthographic view showing objects at the focus distance
+    /// at the size they had. The view blends into it over the next
+    /// [`PROJECTION_SWITCH_TIME`] of [`Camera::tick`], keeping that size
+    /// throughout.
+    pub fn switch_to_orthographic(&mut self) {
+        let height = self.view_height(self.focus_distance());
+        self.projection = Projection::Orthographic { height };
+        self.is_dirty = true;
+    }
+
+    /// Switch to the perspective view, moving the camera to or from the point
+    /// at the focus distance so objects there keep their size on screen. The
+    /// view blends back from orthographic like
+    /// [`Camera::switch_to_orthographic`] blends into it.
+    pub fn switch_to_perspective(&mut self) {
+        let distance = self.focus_distance();
+        let height = self.view_height(distance);
+        let forward = self.forward();
+        let target = self.position + forward * distance;
+        self.projection = Projection::Perspective;
+        self.orbit.distance = height / (2. * (self.fov / 2.).tan());
+        self.position = target - forward * self.orbit.distance;
+        self.is_dirty = true;
+    }
+
+    /// Move the camera along its line of sight until `bounds` fits in the
+    /// field of view, and fit the clip planes around it. The centre of
+    /// `bounds` becomes the orbit target.
+    pub fn frame(&mut self, bounds: &BoundingSphere) {
+        let half_fov = (self.fov / 2.).min((self.aspect_ratio * (self.fov / 2.).tan()).atan());
+        let radius = bounds.radius.max(1e-3);
+        let distance = radius / half_fov.sin();
+        self.position = bounds.center - self.forward() * distance;
+        self.clip = (distance - radius).max(distance * 1e-3)..distance + radius;
+        self.orbit.target = bounds.center;
+        self.orbit.distance = distance;
+        if let Projection::Orthographic { height } = &mut self.projection {
+            *height = 2. * radius / self.aspect_ratio.min(1.);
+        }
+        self.is_dirty = true;
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switch between the free and orbit cameras without moving the view:
+    /// the orbit target is put [`Orbit::distance`] in front of the camera.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == self.mode {
+            return;
+        }
+        self.mode = mode;
+        if mode == CameraMode::Orbit {
+            let forward = self.forward();
+            let orbit = &mut self.orbit;
+            orbit.target = self.position + forward * orbit.distance;
+            orbit.yaw = (-forward.x).atan2(-forward.z);
+            orbit.pitch = (-forward.y).clamp(-1., 1.).asin();
+            self.place_on_orbit();
+        }
+    }
+
+    pub fn orbit(&self) -> &Orbit {
+        &self.orbit
+    }
+
+    /// Turn around the orbit target by `yaw` and `pitch` radians, within the
+    /// orbit limits. Does nothing in [`CameraMode::Free`].
+    pub fn orbit_by(&mut self, yaw: f32, pitch: f32) {
+        if self.mode != CameraMode::Orbit {
+            return;
+        }
+        self.orbit.yaw += yaw;
+        self.orbit.pitch += pitch;
+        self.place_on_orbit();
+    }
+
+    /// Scale the distance to the orbit target by `factor`, and the height of
+    /// an orthographic view with it.
+    pub fn zoom(&mut self, factor: f32) {
+        if self.mode != CameraMode::Orbit {
+            return;
+        }
+        let distance = self.orbit.distance;
+        self.orbit.distance *= factor;
+        self.place_on_orbit();
+        if let Projection::Orthographic { height } = &mut self.projection {
+            *height *= self.orbit.distance / distance;
+        }
+    }
+
+    /// Move the orbit target and the camera with it across the view, by
+    /// `offset` times the distance to the target.
+    pub fn pan(&mut self, offset: Vec2) {
+        if self.mode != CameraMode::Orbit {
+            return;
+        }
+        let offset = self.orientation * Vec3::new(offset.x, offset.y, 0.);
+        self.orbit.target += offset * self.orbit.distance;
+        self.place_on_orbit();
+    }
+
+    fn place_on_orbit(&mut self) {
+        self.orbit.clamp();
+        self.position = self.orbit.eye();
+        self.orientation = looking_at(self.position, self.orbit.target, Vec3::Y);
         self.is_dirty = true;
     }
 
+    /// the direction the camera looks at, in world space
+    pub fn forward(&self) -> Vec3 {
+        self.orientation * Vec3::NEG_Z
+    }
+
     pub fn view_matrix(&self) -> &Mat4 {
         &self.view_m
     }
@@ -129,25 +682,657 @@ impl Camera {
         &self.position
     }
 
-    pub fn on_keyboard_event(&mut self, event: KeyEvent) {
-        if let PhysicalKey::Code(code) = event.physical_key {
-            match code {
-                KeyCode::KeyW => self.moving(Vec3::Y * self.move_sensitivity),
-                KeyCode::KeyS => self.moving(-Vec3::Y * self.move_sensitivity),
-                KeyCode::KeyA => self.moving(-Vec3::X * self.move_sensitivity),
-                KeyCode::KeyD => self.moving(Vec3::X * self.move_sensitivity),
-                KeyCode::KeyQ => self.moving(-Vec3::Z * self.move_sensitivity),
-                KeyCode::KeyE => self.moving(Vec3::Z * self.move_sensitivity),
-
-                KeyCode::KeyI => self.rotating(Vec3::Y * self.rotate_sensitivity),
-                KeyCode::KeyK => self.rotating(-Vec3::Y * self.rotate_sensitivity),
-                KeyCode::KeyL => self.rotating(Vec3::X * self.rotate_sensitivity),
-                KeyCode::KeyJ => self.rotating(-Vec3::X * self.rotate_sensitivity),
-                KeyCode::KeyU => self.rotating(Vec3::Z * self.rotate_sensitivity),
-                KeyCode::KeyO => self.rotating(-Vec3::Z * self.rotate_sensitivity),
+    /// vertical field of view in radians
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
 
+    /// Track held keys for [`Camera::tick`], and apply the actions bound to
+    /// a newly pressed key that switch the mode or the projection or reset
+    /// the view.
+    pub fn on_keyboard_event(&mut self, event: KeyEvent) {
+        let PhysicalKey::Code(key) = event.physical_key else {
+            return;
+        };
+        if !self.input.on_keyboard_event(&event) {
+            return;
+        }
+        let actions: Vec<_> = self
+            .key_bindings
+            .triggered(&self.input, self.mode, key)
+            .collect();
+        for action in actions {
+            match action {
+                Action::ToggleProjection => match self.projection {
+                    Projection::Orthographic { .. } => self.switch_to_perspective(),
+                    _ => self.switch_to_orthographic(),
+                },
+                Action::ToggleMode => self.set_mode(match self.mode {
+                    CameraMode::Free => CameraMode::Orbit,
+                    CameraMode::Orbit => CameraMode::Free,
+                }),
+                Action::Reset => self.reset(),
                 _ => {}
             }
         }
     }
+
+    pub fn key_bindings(&self) -> &KeyBindings {
+        &self.key_bindings
+    }
+
+    /// Remember the current view for [`Camera::reset`].
+    pub fn set_home(&mut self) {
+        self.home = Some(Home {
+            position: self.position,
+            orientation: self.orientation,
+            projection: self.projection,
+            orbit: self.orbit.clone(),
+        });
+    }
+
+    /// Go back to the view saved by [`Camera::set_home`], if any, and stop.
+    pub fn reset(&mut self) {
+        let Some(home) = self.home.clone() else {
+            return;
+        };
+        self.position = home.position;
+        self.orientation = home.orientation;
+        self.projection = home.projection;
+        self.orthographic_blend = self.orthographic_target();
+        self.orbit = home.orbit;
+        self.velocity = Vec3::ZERO;
+        self.angular_velocity = Vec3::ZERO;
+        // put the orbit target back in front of the camera
+        let mode = self.mode;
+        self.mode = CameraMode::Free;
+        self.set_mode(mode);
+        self.is_dirty = true;
+    }
+
+    pub fn input(&self) -> &InputState {
+        &self.input
+    }
+
+    pub fn input_mut(&mut self) -> &mut InputState {
+        &mut self.input
+    }
+
+    /// Advance the keyboard controls by `dt` seconds, as bound by
+    /// [`Camera::with_key_bindings`]. The free camera moves and turns around
+    /// its own axes; the orbit camera pans, zooms by moving forward and
+    /// backward, and turns around its target.
+    ///
+    /// Held keys accelerate towards the full speed and released ones let it
+    /// decay, the same way whatever the frame rate. A projection switch
+    /// blends in over the ticks too.
+    pub fn tick(&mut self, dt: f32) {
+        let target = self.orthographic_target();
+        if self.orthographic_blend != target {
+            let step = dt / PROJECTION_SWITCH_TIME;
+            self.orthographic_blend = match target > self.orthographic_blend {
+                true => (self.orthographic_blend + step).min(target),
+                false => (self.orthographic_blend - step).max(target),
+            };
+            self.is_dirty = true;
+        }
+
+        let (bindings, input, mode) = (&self.key_bindings, &self.input, self.mode);
+        let axis = |negative, positive| bindings.axis(input, mode, negative, positive);
+        let fast = match bindings.is_active(input, mode, Action::Fast) {
+            true => FAST_FACTOR,
+            false => 1.,
+        };
+        let linear = Vec3::new(
+            axis(Action::StrafeLeft, Action::StrafeRight),
+            axis(Action::MoveDown, Action::MoveUp),
+            axis(Action::MoveForward, Action::MoveBackward),
+        ) * fast;
+        // pitch, yaw and roll, counter-clockwise around the view axes
+        let angular = Vec3::new(
+            axis(Action::TurnDown, Action::TurnUp),
+            axis(Action::TurnRight, Action::TurnLeft),
+            axis(Action::RollRight, Action::RollLeft),
+        );
+        let zoom = axis(Action::ZoomOut, Action::ZoomIn);
+        self.velocity = approach(
+            self.velocity,
+            linear,
+            self.acceleration * fast,
+            self.damping,
+            dt,
+        );
+        self.angular_velocity = approach(
+            self.angular_velocity,
+            angular,
+            self.acceleration,
+            self.damping,
+            dt,
+        );
+
+        if zoom != 0. {
+            self.zoom_fov((-zoom * ZOOM_SPEED * dt).exp());
+        }
+
+        let (moved, turned) = (self.velocity * dt, self.angular_velocity * dt);
+        match self.mode {
+            CameraMode::Free => {
+                if moved != Vec3::ZERO {
+                    self.moving(moved * self.move_sensitivity);
+                }
+                if turned != Vec3::ZERO {
+                    self.rotating(turned * self.rotate_sensitivity);
+                }
+            }
+            CameraMode::Orbit => {
+                let turned = turned * self.rotate_sensitivity;
+                let moved = moved * ORBIT_SPEED;
+                if turned.x != 0. || turned.y != 0. {
+                    self.orbit_by(turned.y, -turned.x);
+                }
+                if moved.x != 0. || moved.y != 0. {
+                    self.pan(moved.truncate());
+                }
+                if moved.z != 0. {
+                    self.zoom(moved.z.exp());
+                }
+            }
+        }
+    }
+
+    /// Mouse controls besides [`Camera::look`]: the scroll wheel zooms as
+    /// set by [`Camera::with_scroll_zoom`] and, in orbit mode, dragging with
+    /// the left button turns around the target and with the right or middle
+    /// button pans.
+    pub fn on_mouse_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::MouseInput { state, button, .. } => {
+                if state.is_pressed() {
+                    self.drag = Some(button);
+                } else if self.drag == Some(button) {
+                    self.drag = None;
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let last = self.cursor.replace(position);
+                let (Some(button), Some(last)) = (self.drag, last) else {
+                    return;
+                };
+                let delta = Vec2::new((position.x - last.x) as f32, (position.y - last.y) as f32);
+                match button {
+                    MouseButton::Left => self.orbit_by(
+                        -delta.x * self.mouse_sensitivity,
+                        delta.y * self.mouse_sensitivity,
+                    ),
+                    MouseButton::Right | MouseButton::Middle => {
+                        self.pan(Vec2::new(-delta.x, delta.y) * PAN_PER_PIXEL)
+                    }
+                    _ => {}
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                self.drag = None;
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 * LINES_PER_PIXEL,
+                };
+                let factor = (1. - self.zoom_sensitivity).powf(lines);
+                match (self.scroll_zoom, self.mode) {
+                    (ScrollZoom::Fov, _) => self.zoom_fov(factor),
+                    (ScrollZoom::Dolly, CameraMode::Orbit) => self.zoom(factor),
+                    (ScrollZoom::Dolly, CameraMode::Free) => {
+                        self.moving(Vec3::NEG_Z * lines * SECONDS_PER_LINE * self.move_sensitivity)
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `velocity` after accelerating towards `target` for `dt` seconds, or
+/// decaying when no key is held.
+fn approach(velocity: Vec3, target: Vec3, acceleration: f32, damping: f32, dt: f32) -> Vec3 {
+    if target == Vec3::ZERO {
+        let velocity = velocity * (-damping * dt).exp();
+        // stop instead of creeping forever
+        return if velocity.length_squared() < 1e-8 {
+            Vec3::ZERO
+        } else {
+            velocity
+        };
+    }
+    let delta = target - velocity;
+    let step = acceleration * dt;
+    if delta.length() <= step {
+        target
+    } else {
+        velocity + delta.normalize() * step
+    }
+}
+
+/// Rotation from view space to world space of a camera at `eye` looking at
+/// `target`.
+fn looking_at(eye: Vec3, target: Vec3, up: Vec3) -> Quat {
+    let forward = (target - eye).normalize();
+    let right = forward.cross(up).normalize();
+    let up = right.cross(forward);
+    Quat::from_mat3(&Mat3::from_cols(right, up, -forward))
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::keyboard::KeyCode;
+
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-4), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn orbit_looks_at_its_target_within_limits() {
+        let target = Vec3::new(1., 2., 3.);
+        let mut camera = Camera::new().with_orbit(Orbit {
+            target,
+            distance: 5.,
+            yaw_limits: Some(-1.0..1.0),
+            pitch_limits: -0.5..1.2,
+            distance_limits: 2.0..8.0,
+            ..Default::default()
+        });
+        assert_close(*camera.position(), target + Vec3::Z * 5.);
+        assert_close(camera.forward(), -Vec3::Z);
+
+        camera.orbit_by(3., -2.);
+        assert_eq!(camera.orbit().yaw, 1.);
+        assert_eq!(camera.orbit().pitch, -0.5);
+        camera.zoom(10.);
+        assert_eq!(camera.orbit().distance, 8.);
+        let eye = *camera.position();
+        assert!((eye.distance(target) - 8.).abs() < 1e-4);
+        assert!(eye.y < target.y);
+        assert_close(camera.forward(), (target - eye).normalize());
+
+        camera.pan(Vec2::new(0.1, 0.));
+        let moved = camera.orbit().target - target;
+        assert!((moved.length() - 0.8).abs() < 1e-4);
+        assert!(moved.dot(camera.forward()).abs() < 1e-4);
+        assert_close(*camera.position(), eye + moved);
+    }
+
+    #[test]
+    fn switching_modes_keeps_the_view() {
+        let bounds = BoundingSphere {
+            center: Vec3::new(0., 1., 0.),
+            radius: 1.,
+        };
+        let mut camera = Camera::new()
+            .with_position(Vec3::new(4., 3., 4.))
+            .with_up_lookat(Vec3::Y, Vec3::ZERO)
+            .with_framing(&bounds);
+        let (position, forward) = (*camera.position(), camera.forward());
+
+        camera.set_mode(CameraMode::Orbit);
+        assert_close(camera.orbit().target, bounds.center);
+        assert_close(*camera.position(), position);
+        assert_close(camera.forward(), forward);
+
+        camera.orbit_by(0.5, 0.);
+        camera.set_mode(CameraMode::Free);
+        let position = *camera.position();
+        camera.orbit_by(0.5, 0.);
+        camera.zoom(2.);
+        assert_eq!(*camera.position(), position);
+    }
+
+    #[test]
+    fn mouse_look_yaws_around_world_up() {
+        let mut camera = Camera::new().with_mouse_sensitivity(0.01);
+        assert_close(camera.forward(), -Vec3::Z);
+        // moving the mouse right then down turns right then looks down
+        camera.look(Vec2::new(50., 0.));
+        let right = camera.forward();
+        assert!(right.x > 0. && right.y.abs() < 1e-5);
+        camera.look(Vec2::new(0., 50.));
+        let down = camera.forward();
+        assert!(down.y < 0.);
+        assert!((down.x / down.z - right.x / right.z).abs() < 1e-4);
+        // yawing again keeps the horizon level
+        camera.look(Vec2::new(-120., 0.));
+        assert!((camera.forward().y - down.y).abs() < 1e-5);
+
+        // looking far up stops short of the zenith, and yaw still turns right
+        camera.look(Vec2::new(0., -1000.));
+        let up = camera.forward();
+        assert!((up.y - 89_f32.to_radians().sin()).abs() < 1e-4, "{}", up);
+        assert!((camera.orientation * Vec3::Y).y > 0.);
+        camera.look(Vec2::new(50., 0.));
+        let turned = camera.forward();
+        assert!(up.cross(turned).y < 0.);
+
+        camera.zoom_fov(0.);
+        assert_eq!(camera.fov(), 1_f32.to_radians());
+    }
+
+    /// Normalised device coordinates of a point in view space.
+    fn project(camera: &mut Camera, p: Vec3) -> Vec3 {
+        camera.update();
+        camera.perspective_matrix().project_point3(p)
+    }
+
+    #[test]
+    fn projection_switches_keep_the_target_size() {
+        let mut camera = Camera::new().with_aspect_ratio(1.5).with_orbit(Orbit {
+            distance: 4.,
+            ..Default::default()
+        });
+        let edge = Vec3::new(0.3, 0.5, -4.);
+        let size = project(&mut camera, edge);
+
+        camera.switch_to_orthographic();
+        camera.tick(PROJECTION_SWITCH_TIME);
+        assert!(matches!(
+            camera.projection(),
+            Projection::Orthographic { .. }
+        ));
+        let ortho = project(&mut camera, edge);
+        assert!((ortho.x - size.x).abs() < 1e-5 && (ortho.y - size.y).abs() < 1e-5);
+        // orthographic zoom scales the view instead of moving
+        camera.zoom(2.);
+        let zoomed = project(&mut camera, edge);
+        assert!((zoomed.y - size.y / 2.).abs() < 1e-5);
+
+        camera.switch_to_perspective();
+        camera.tick(PROJECTION_SWITCH_TIME);
+        assert_eq!(camera.projection(), Projection::Perspective);
+        assert!((camera.orbit().distance - 8.).abs() < 1e-4);
+        let back = project(&mut camera, edge * Vec3::new(1., 1., 2.));
+        assert!((back.y - size.y / 2.).abs() < 1e-5);
+        assert_close(camera.orbit().target, Vec3::ZERO);
+
+        // the free camera keeps the size of the target it moved towards
+        let mut camera = Camera::new().with_framing(&BoundingSphere {
+            center: Vec3::ZERO,
+            radius: 1.,
+        });
+        camera.moving(Vec3::NEG_Z * 0.5);
+        let (position, edge) = (*camera.position(), Vec3::new(0., 0.5, 0.));
+        let size = clip(&mut camera, edge);
+        camera.switch_to_orthographic();
+        camera.tick(PROJECTION_SWITCH_TIME);
+        let ortho = clip(&mut camera, edge);
+        assert!((ortho.y - size.y).abs() < 1e-5);
+        camera.switch_to_perspective();
+        assert_close(*camera.position(), position);
+    }
+
+    #[test]
+    fn projection_switches_blend_over_ticks() {
+        let mut camera = Camera::new().with_orbit(Orbit {
+            distance: 4.,
+            ..Default::default()
+        });
+        // on the target plane, and halfway to the camera; depths may change
+        let (edge, near) = (Vec3::new(0.3, 0.5, 0.), Vec3::new(0.3, 0.5, 2.));
+        let size = clip(&mut camera, edge);
+        let mut previous = clip(&mut camera, near);
+        camera.switch_to_orthographic();
+        for _ in 0..4 {
+            assert!((clip(&mut camera, edge) - size).truncate().length() < 1e-4);
+            camera.tick(PROJECTION_SWITCH_TIME / 4.);
+            // nearer objects shrink towards their orthographic size
+            let shown = clip(&mut camera, near);
+            assert!(shown.y < previous.y - 1e-3, "{} {}", shown, previous);
+            previous = shown;
+        }
+        assert!((clip(&mut camera, edge) - size).truncate().length() < 1e-4);
+        assert!((previous.y - size.y).abs() < 1e-4);
+
+        camera.switch_to_perspective();
+        camera.tick(PROJECTION_SWITCH_TIME / 2.);
+        assert!((clip(&mut camera, edge) - size).truncate().length() < 1e-4);
+        camera.tick(PROJECTION_SWITCH_TIME / 2.);
+        assert!((clip(&mut camera, edge) - size).truncate().length() < 1e-4);
+    }
+
+    #[test]
+    fn off_centre_frustum_maps_its_sides_to_the_viewport_edges() {
+        let fov = 60_f32;
+        let tan = (fov.to_radians() / 2.).tan();
+        let mut symmetric = Camera::new()
+            .with_fov(fov)
+            .with_projection(Projection::OffCentre {
+                left: -tan,
+                right: tan,
+                bottom: -tan,
+                top: tan,
+            });
+        symmetric.update();
+        let mut perspective = Camera::new().with_fov(fov);
+        perspective.update();
+        assert!(symmetric
+            .perspective_matrix()
+            .abs_diff_eq(*perspective.perspective_matrix(), 1e-6));
+
+        let mut camera = Camera::new().with_projection(Projection::OffCentre {
+            left: -0.2,
+            right: 0.6,
+            bottom: -0.1,
+            top: 0.3,
+        });
+        assert!((camera.view_height(10.) - 4.).abs() < 1e-5);
+        let corner = project(&mut camera, Vec3::new(0.6, 0.3, -1.) * 5.);
+        assert!(corner.abs_diff_eq(Vec3::new(1., 1., corner.z), 1e-5));
+        let corner = project(&mut camera, Vec3::new(-0.2, -0.1, -1.) * 5.);
+        assert!(corner.abs_diff_eq(Vec3::new(-1., -1., corner.z), 1e-5));
+    }
+
+    /// Deterministic xorshift samples in `-1..1`, for property tests.
+    struct Samples(u32);
+
+    impl Samples {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 as f32 / u32::MAX as f32 * 2. - 1.
+        }
+
+        fn vec3(&mut self, scale: f32) -> Vec3 {
+            Vec3::new(self.next(), self.next(), self.next()) * scale
+        }
+    }
+
+    /// Clip space position of a world space point.
+    fn clip(camera: &mut Camera, p: Vec3) -> Vec3 {
+        camera.update();
+        (*camera.perspective_matrix() * *camera.view_matrix()).project_point3(p)
+    }
+
+    #[test]
+    fn look_at_targets_project_to_the_screen_centre() {
+        let mut samples = Samples(0x9e37_79b9);
+        for _ in 0..500 {
+            let position = samples.vec3(50.);
+            let target = position + samples.vec3(20.);
+            let up = samples.vec3(1.);
+            let distance = position.distance(target);
+            if distance < 0.1 || up.cross(target - position).length() < 0.1 {
+                continue;
+            }
+            let mut camera = Camera::new()
+                .with_position(position)
+                .with_up_lookat(up, target)
+                .with_clip(0.01..100.);
+            assert_close(camera.forward(), (target - position) / distance);
+
+            let centre = clip(&mut camera, target);
+            assert!(centre.truncate().length() < 1e-4, "{}", centre);
+            assert!(centre.z > -1. && centre.z < 1.);
+            // a point behind the camera is outside the depth range
+            let behind = clip(&mut camera, position * 2. - target);
+            assert!(behind.z.abs() > 1.);
+            // `up` points up on screen
+            let above = clip(&mut camera, target + up.normalize() * distance * 0.1);
+            assert!(above.y > 0. && above.x.abs() < 1e-3, "{}", above);
+
+            let view = *camera.view_matrix();
+            assert_close(view.transform_point3(position), Vec3::ZERO);
+            assert_close(
+                view.transform_point3(position + camera.forward()),
+                Vec3::NEG_Z,
+            );
+        }
+    }
+
+    #[test]
+    fn orbit_and_moves_keep_the_convention() {
+        let mut samples = Samples(0x1234_5678);
+        for _ in 0..200 {
+            let target = samples.vec3(10.);
+            let mut camera = Camera::new().with_orbit(Orbit {
+                target,
+                yaw: samples.next() * PI,
+                pitch: samples.next() * 1.5,
+                distance: 1. + samples.next().abs() * 20.,
+                ..Default::default()
+            });
+            camera.orbit_by(samples.next(), samples.next());
+            let centre = clip(&mut camera, target);
+            assert!(centre.truncate().length() < 1e-4, "{}", centre);
+
+            // moving along -Z in view space goes forward, +X to the right
+            camera.set_mode(CameraMode::Free);
+            let (position, forward) = (*camera.position(), camera.forward());
+            camera.moving(Vec3::NEG_Z);
+            assert_close(*camera.position(), position + forward);
+            let ahead = *camera.position() + camera.forward() * 2.;
+            let right = ahead + camera.orientation * Vec3::X;
+            assert!(clip(&mut camera, right).x > 0.);
+        }
+    }
+
+    #[test]
+    fn viewport_sets_a_clamped_aspect_ratio() {
+        let mut camera = Camera::new();
+        camera.set_viewport(1600, 1200);
+        assert_eq!(camera.viewport(), (1600, 1200));
+        assert_eq!(camera.aspect_ratio(), 4. / 3.);
+
+        // minimised
+        camera.set_viewport(0, 1200);
+        assert_eq!(camera.viewport(), (1600, 1200));
+        assert_eq!(camera.aspect_ratio(), 4. / 3.);
+
+        camera.set_viewport(5000, 10);
+        assert_eq!(camera.viewport(), (5000, 10));
+        assert_eq!(camera.aspect_ratio(), 10.);
+        camera.set_aspect_ratio(f32::NAN);
+        assert_eq!(camera.aspect_ratio(), 10.);
+        let camera = Camera::new().with_aspect_ratio(1e-4);
+        assert_eq!(camera.aspect_ratio(), 0.1);
+    }
+
+    #[test]
+    fn ticks_move_the_same_at_any_frame_rate() {
+        let run = |hz: u32| {
+            let mut camera = Camera::new();
+            camera.input_mut().set_held(KeyCode::KeyD, true);
+            camera.input_mut().set_held(KeyCode::KeyI, true);
+            for _ in 0..hz {
+                camera.tick(1. / hz as f32);
+            }
+            camera.input_mut().clear();
+            for _ in 0..hz {
+                camera.tick(1. / hz as f32);
+            }
+            (*camera.position(), camera.forward(), camera.velocity)
+        };
+        let (position, forward, velocity) = run(60);
+        let (position_fast, forward_fast, _) = run(240);
+        assert!(position.x > 5., "{}", position);
+        assert!(
+            position.distance(position_fast) < 0.1,
+            "{} {}",
+            position,
+            position_fast
+        );
+        assert!(forward.distance(forward_fast) < 1e-2);
+        // released keys stop the camera
+        assert_eq!(velocity, Vec3::ZERO);
+
+        // orbiting keeps the target in the centre
+        let mut camera = Camera::new().with_mode(CameraMode::Orbit);
+        camera.input_mut().set_held(KeyCode::KeyL, true);
+        camera.input_mut().set_held(KeyCode::KeyQ, true);
+        for _ in 0..30 {
+            camera.tick(1. / 30.);
+        }
+        let target = camera.orbit().target;
+        assert!(camera.orbit().distance < Orbit::default().distance);
+        assert!(clip(&mut camera, target).truncate().length() < 1e-4);
+    }
+
+    #[test]
+    fn zoom_keys_change_the_field_of_view() {
+        let mut camera = Camera::new();
+        let (fov, position) = (camera.fov(), *camera.position());
+        camera.input_mut().set_held(KeyCode::KeyZ, true);
+        for _ in 0..10 {
+            camera.tick(0.05);
+        }
+        assert!((camera.fov() - fov * (-0.5_f32).exp()).abs() < 1e-4);
+        camera.input_mut().clear();
+        camera.input_mut().set_held(KeyCode::KeyX, true);
+        for _ in 0..10 {
+            camera.tick(0.05);
+        }
+        assert!((camera.fov() - fov).abs() < 1e-4);
+        assert_eq!(*camera.position(), position);
+    }
+
+    #[test]
+    fn shift_moves_faster_and_reset_goes_home() {
+        let travel = |keys: &[KeyCode]| {
+            let mut camera = Camera::new();
+            camera.set_home();
+            for &key in keys {
+                camera.input_mut().set_held(key, true);
+            }
+            for _ in 0..60 {
+                camera.tick(1. / 60.);
+            }
+            let travelled = camera.position().distance(Camera::new().position);
+            camera.reset();
+            assert_eq!(*camera.position(), Camera::new().position);
+            assert_eq!(camera.velocity, Vec3::ZERO);
+            travelled
+        };
+        let walk = travel(&[KeyCode::KeyQ]);
+        let run = travel(&[KeyCode::KeyQ, KeyCode::ShiftLeft]);
+        assert!(run > 3. * walk, "{} {}", walk, run);
+
+        // rebound keys replace the defaults
+        let bindings = "move_forward = \"W\"".parse().unwrap();
+        let mut camera = Camera::new().with_key_bindings(bindings);
+        camera.input_mut().set_held(KeyCode::KeyQ, true);
+        camera.tick(0.1);
+        assert_eq!(*camera.position(), Camera::new().position);
+        camera.input_mut().set_held(KeyCode::KeyW, true);
+        camera.tick(0.1);
+        assert!(camera.position().z < Camera::new().position.z);
+    }
+
+    #[test]
+    #[should_panic(expected = "distance limits")]
+    fn orbit_distance_limits_must_be_positive() {
+        let _ = Camera::new().with_orbit(Orbit {
+            distance_limits: 0.0..10.,
+            ..Default::default()
+        });
+    }
 }
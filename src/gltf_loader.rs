@@ -0,0 +1,324 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use glam::{Mat3, Mat4, Vec3};
+use gltf::{buffer, image, mesh::Mode, Document};
+
+use crate::mesh::{LoadStats, Material, Mesh, Model, NormalMode, SubMesh, Vertex};
+
+#[derive(Debug)]
+pub enum GltfLoadError {
+    /// The file or one of its buffers could not be read or is not valid glTF.
+    Gltf { path: PathBuf, source: gltf::Error },
+    /// A primitive has no `POSITION` attribute.
+    MissingPositions { mesh: String },
+}
+
+impl fmt::Display for GltfLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfLoadError::Gltf { path, source } => {
+                write!(f, "failed to load {}: {}", path.display(), source)
+            }
+            GltfLoadError::MissingPositions { mesh } => {
+                write!(f, "a primitive of mesh {:?} has no positions", mesh)
+            }
+        }
+    }
+}
+
+impl Error for GltfLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GltfLoadError::Gltf { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Load a `.gltf` or `.glb` file, panicking on any error. Meant for the
+/// examples, use [`try_load_gltf`] otherwise.
+pub fn load_gltf<P>(path: P) -> Model
+where
+    P: AsRef<Path> + Debug,
+{
+    try_load_gltf(&path).unwrap_or_else(|e| panic!("Failed to load glTF file {:?}: {}", path, e))
+}
+
+/// Load the default scene of a `.gltf` (with external or embedded buffers) or
+/// `.glb` file. Every triangle primitive becomes a submesh, with node
+/// transforms baked into the vertices; points and lines are skipped.
+pub fn try_load_gltf<P>(path: P) -> Result<Model, GltfLoadError>
+where
+    P: AsRef<Path>,
+{
+    let start = Instant::now();
+    let path = path.as_ref();
+    let gltf_error = |source| GltfLoadError::Gltf {
+        path: path.to_owned(),
+        source,
+    };
+    let gltf = gltf::Gltf::open(path).map_err(gltf_error)?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    let buffers =
+        gltf::import_buffers(&gltf.document, Some(base), gltf.blob.clone()).map_err(gltf_error)?;
+    let model = load_document(&gltf.document, &buffers, base)?;
+    log::info!(
+        "Loaded {}: {}",
+        path.display(),
+        LoadStats::new(&model, start.elapsed())
+    );
+    Ok(model)
+}
+
+fn load_document(
+    document: &Document,
+    buffers: &[buffer::Data],
+    base: &Path,
+) -> Result<Model, GltfLoadError> {
+    let mut model = Model {
+        materials: document
+            .materials()
+            .map(|m| convert_material(&m, base))
+            .collect(),
+        ..Default::default()
+    };
+
+    let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    else {
+        return Ok(model);
+    };
+    let mut stack = scene
+        .nodes()
+        .map(|node| (node, Mat4::IDENTITY))
+        .collect::<Vec<_>>();
+    while let Some((node, parent)) = stack.pop() {
+        let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            let name = mesh
+                .name()
+                .or(node.name())
+                .map(str::to_owned)
+                .unwrap_or_else(|| format!("mesh{}", mesh.index()));
+            for primitive in mesh.primitives() {
+                if let mode @ (Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip) =
+                    primitive.mode()
+                {
+                    log::warn!("Skipping a primitive of mesh {:?} made of {:?}", name, mode);
+                    continue;
+                }
+                let part = load_primitive(&primitive, buffers, transform, &name)?;
+                let start = model.mesh.indices.len() as u32;
+                model.mesh.append(part);
+                model.submeshes.push(SubMesh {
+                    name: name.clone(),
+                    indices: start..model.mesh.indices.len() as u32,
+                    material: primitive.material().index(),
+                });
+            }
+        }
+        stack.extend(node.children().map(|child| (child, transform)));
+    }
+    Ok(model)
+}
+
+fn load_primitive(
+    primitive: &gltf::Primitive,
+    buffers: &[buffer::Data],
+    transform: Mat4,
+    name: &str,
+) -> Result<Mesh, GltfLoadError> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+    let positions = reader
+        .read_positions()
+        .ok_or_else(|| GltfLoadError::MissingPositions {
+            mesh: name.to_owned(),
+        })?;
+
+    let mut mesh = Mesh {
+        vertices: positions.map(|p| Vertex::new(p, [0.; 3])).collect(),
+        ..Default::default()
+    };
+    if let Some(normals) = reader.read_normals() {
+        mesh.vertices
+            .iter_mut()
+            .zip(normals)
+            .for_each(|(v, n)| v.normal = n);
+        mesh.attributes.normals = true;
+    }
+    if let Some(tex_coords) = reader.read_tex_coords(0) {
+        mesh.vertices
+            .iter_mut()
+            .zip(tex_coords.into_f32())
+            .for_each(|(v, uv)| v.tex_coords = uv);
+        mesh.attributes.tex_coords = true;
+    }
+    if let Some(colors) = reader.read_colors(0) {
+        mesh.vertices
+            .iter_mut()
+            .zip(colors.into_rgba_f32())
+            .for_each(|(v, color)| v.color = color);
+        mesh.attributes.colors = true;
+    }
+    if let Some(tangents) = reader.read_tangents() {
+        mesh.vertices.iter_mut().zip(tangents).for_each(|(v, t)| {
+            let tangent = Vec3::from_slice(&t[..3]);
+            v.tangent = t;
+            v.bitangent = (Vec3::from(v.normal).cross(tangent) * t[3]).to_array();
+        });
+        mesh.attributes.tangents = true;
+    }
+
+    let n = mesh.vertices.len() as u32;
+    let indices = reader
+        .read_indices()
+        .map(|indices| indices.into_u32().collect())
+        .unwrap_or_else(|| (0..n).collect::<Vec<_>>());
+    mesh.indices = match primitive.mode() {
+        Mode::Triangles => indices,
+        Mode::TriangleStrip => (2..indices.len())
+            .flat_map(|i| {
+                let (a, b, c) = (indices[i - 2], indices[i - 1], indices[i]);
+                if i % 2 == 0 {
+                    [a, b, c]
+                } else {
+                    [b, a, c]
+                }
+            })
+            .collect(),
+        Mode::TriangleFan => (2..indices.len())
+            .flat_map(|i| [indices[0], indices[i - 1], indices[i]])
+            .collect(),
+        // skipped by `load_document`
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => Vec::new(),
+    };
+    mesh.indices.truncate(mesh.indices.len() / 3 * 3);
+
+    // the spec asks for flat normals when they are missing
+    if !mesh.attributes.normals {
+        mesh.generate_normals(NormalMode::Flat);
+    }
+    if mesh.attributes.tex_coords && !mesh.attributes.tangents {
+        mesh.generate_tangents();
+    }
+    apply_transform(&mut mesh, transform);
+    Ok(mesh)
+}
+
+fn apply_transform(mesh: &mut Mesh, transform: Mat4) {
+    if transform == Mat4::IDENTITY {
+        return;
+    }
+    let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+    let linear = Mat3::from_mat4(transform);
+    for v in &mut mesh.vertices {
+        v.position = transform.transform_point3(v.position.into()).to_array();
+        let normal = (normal_matrix * Vec3::from(v.normal)).normalize_or_zero();
+        let tangent = (linear * Vec3::from_slice(&v.tangent[..3])).normalize_or_zero();
+        v.normal = normal.to_array();
+        v.tangent = tangent.extend(v.tangent[3]).to_array();
+        v.bitangent = (normal.cross(tangent) * v.tangent[3]).to_array();
+    }
+    // mirroring transforms flip the winding
+    if linear.determinant() < 0. {
+        mesh.indices
+            .chunks_exact_mut(3)
+            .for_each(|tri| tri.swap(1, 2));
+    }
+}
+
+fn convert_material(m: &gltf::Material, base: &Path) -> Material {
+    let texture_path = |texture: gltf::Texture| match texture.source().source() {
+        image::Source::Uri { uri, .. } if !uri.starts_with("data:") => Some(base.join(uri)),
+        _ => None,
+    };
+    let pbr = m.pbr_metallic_roughness();
+    let [r, g, b, a] = pbr.base_color_factor();
+    Material {
+        name: m.name().unwrap_or_default().to_owned(),
+        diffuse: [r, g, b],
+        dissolve: a,
+        metallic: pbr.metallic_factor(),
+        roughness: pbr.roughness_factor(),
+        diffuse_texture: pbr
+            .base_color_texture()
+            .and_then(|info| texture_path(info.texture())),
+        metallic_roughness_texture: pbr
+            .metallic_roughness_texture()
+            .and_then(|info| texture_path(info.texture())),
+        normal_texture: m
+            .normal_texture()
+            .and_then(|info| texture_path(info.texture())),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn gltf_with_external_buffer() {
+        let model = try_load_gltf(fixture("triangle.gltf")).unwrap();
+        let mesh = &model.mesh;
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert!(mesh.attributes.normals && mesh.attributes.tex_coords && mesh.attributes.tangents);
+        // node translation is baked in
+        assert_eq!(mesh.vertices[0].position, [1., 0., 0.]);
+        assert_eq!(mesh.vertices[2].tex_coords, [0., 1.]);
+        assert_eq!(mesh.vertices[0].tangent, [1., 0., 0., 1.]);
+
+        assert_eq!(model.submeshes.len(), 1);
+        let material = model.material(&model.submeshes[0]).unwrap();
+        assert_eq!(material.name, "red");
+        assert_eq!(material.diffuse, [1., 0., 0.]);
+        assert_eq!((material.metallic, material.roughness), (0.5, 0.25));
+        assert_eq!(material.diffuse_texture, Some(fixture("red.png")));
+    }
+
+    #[test]
+    fn glb_with_node_hierarchy() {
+        let model = try_load_gltf(fixture("quad.glb")).unwrap();
+        let mesh = &model.mesh;
+        assert_eq!(model.submeshes[0].name, "quad");
+        assert_eq!(model.submeshes[0].material, None);
+        assert_eq!(mesh.indices.len(), 6);
+        // the parent scale applies to the child translation
+        let aabb = mesh.aabb();
+        assert_eq!(aabb.min, Vec3::new(0., 0., 2.));
+        assert_eq!(aabb.max, Vec3::new(2., 2., 2.));
+        // missing normals are generated flat
+        assert!(mesh.vertices.iter().all(|v| v.normal == [0., 0., 1.]));
+    }
+
+    #[test]
+    fn points_and_lines_are_skipped_and_colours_read() {
+        let model = try_load_gltf(fixture("colored.gltf")).unwrap();
+        assert_eq!(model.submeshes.len(), 1);
+        assert_eq!(model.submeshes[0].indices, 0..3);
+        let mesh = &model.mesh;
+        assert!(mesh.attributes.colors);
+        assert_eq!(mesh.vertices[1].color, [1., 0., 0., 1.]);
+        assert_eq!(mesh.vertices[2].color, [0., 1., 0., 1.]);
+    }
+
+    #[test]
+    fn missing_file_is_reported() {
+        assert!(matches!(
+            try_load_gltf(fixture("missing.gltf")),
+            Err(GltfLoadError::Gltf { .. })
+        ));
+    }
+}
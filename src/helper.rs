@@ -3,15 +3,63 @@ use std::fs::File;
 use std::io::{self, Read};
 use std::path::Path;
 use std::rc::Rc;
+use std::time::Instant;
 
+use glam::Vec2;
 use glium::glutin::surface::WindowSurface;
 use glium::{Display, Program, ProgramCreationError};
-use winit::event::WindowEvent;
+use log::{LevelFilter, Log, Metadata, Record};
+use winit::dpi::PhysicalSize;
+use winit::event::{DeviceEvent, ElementState, MouseButton, WindowEvent};
 use winit::event_loop::EventLoop;
-use winit::window::Window;
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::{CursorGrabMode, Window};
 
-use crate::camera::Camera;
+use crate::camera::{Camera, CameraMode};
 
+/// Print `log` records to stderr, at the level named by the `RUST_LOG`
+/// environment variable or `info` by default.
+pub fn init_logger() {
+    static LOGGER: StderrLogger = StderrLogger;
+    let level = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(LevelFilter::Info);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Longest frame time passed to [`Camera::tick`], so a stall such as dragging
+/// the window does not throw the camera across the scene.
+const MAX_TICK: f32 = 0.1;
+
+/// Run the window until it is closed, calling `on_redraw` every frame and
+/// feeding keyboard and mouse input to `camera`, which is ticked with the
+/// time since the previous frame.
+///
+/// The camera follows the size of the framebuffer, including after DPI scale
+/// changes.
+///
+/// Clicking in the window with the free camera captures the pointer for mouse
+/// look, Escape or losing focus releases it. Resetting the camera goes back to
+/// its view when the loop started.
 pub fn event_loop_run<T, F>(
     event_loop: EventLoop<T>,
     display: &Display<WindowSurface>,
@@ -21,22 +69,65 @@ pub fn event_loop_run<T, F>(
 ) where
     F: FnMut(),
 {
+    let mut captured = false;
+    let mut last_frame = Instant::now();
+    let resize = |size: PhysicalSize<u32>| {
+        display.resize(size.into());
+        camera.borrow_mut().set_viewport(size.width, size.height);
+    };
+    resize(window.inner_size());
+    camera.borrow_mut().set_home();
     event_loop
         .run(move |ev, window_target| {
             match ev {
                 winit::event::Event::WindowEvent { event, .. } => match event {
                     WindowEvent::KeyboardInput { event, .. } => {
-                        camera.borrow_mut().on_keyboard_event(event)
+                        if event.physical_key == PhysicalKey::Code(KeyCode::Escape) {
+                            captured = capture_pointer(window, false);
+                        }
+                        camera.borrow_mut().on_keyboard_event(event);
+                        // orbiting drags the visible cursor
+                        if captured && camera.borrow().mode() == CameraMode::Orbit {
+                            captured = capture_pointer(window, false);
+                        }
+                    }
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    } if !captured && camera.borrow().mode() == CameraMode::Free => {
+                        captured = capture_pointer(window, true);
+                    }
+                    WindowEvent::MouseInput { .. }
+                    | WindowEvent::CursorMoved { .. }
+                    | WindowEvent::CursorLeft { .. }
+                    | WindowEvent::MouseWheel { .. } => camera.borrow_mut().on_mouse_event(&event),
+                    WindowEvent::Focused(false) => {
+                        captured = capture_pointer(window, false);
+                        // key releases go to the focused window
+                        camera.borrow_mut().input_mut().clear();
                     }
                     WindowEvent::CloseRequested => {
                         window_target.exit();
                     }
-                    WindowEvent::RedrawRequested => on_redraw(),
-                    WindowEvent::Resized(window_size) => {
-                        display.resize(window_size.into());
+                    WindowEvent::RedrawRequested => {
+                        let now = Instant::now();
+                        let dt = now.duration_since(last_frame).as_secs_f32();
+                        last_frame = now;
+                        camera.borrow_mut().tick(dt.min(MAX_TICK));
+                        on_redraw();
                     }
+                    WindowEvent::Resized(window_size) => resize(window_size),
+                    // platforms that resize the window send a Resized event
+                    // too, others keep the physical size
+                    WindowEvent::ScaleFactorChanged { .. } => resize(window.inner_size()),
                     _ => (),
                 },
+                // raw motion keeps coming when the locked cursor cannot move
+                winit::event::Event::DeviceEvent {
+                    event: DeviceEvent::MouseMotion { delta: (x, y) },
+                    ..
+                } if captured => camera.borrow_mut().look(Vec2::new(x as f32, y as f32)),
                 // By requesting a redraw in response to a AboutToWait event we get continuous rendering.
                 // For applications that only change due to user input we could remove this handler.
                 winit::event::Event::AboutToWait => {
@@ -48,6 +139,29 @@ pub fn event_loop_run<T, F>(
         .expect("Eventloop crash");
 }
 
+/// Lock and hide the cursor, or give it back. Platforms that cannot lock it
+/// confine it to the window instead. Returns whether the pointer is captured.
+fn capture_pointer(window: &Window, capture: bool) -> bool {
+    if !capture {
+        let _ = window.set_cursor_grab(CursorGrabMode::None);
+        window.set_cursor_visible(true);
+        return false;
+    }
+    let grabbed = window
+        .set_cursor_grab(CursorGrabMode::Locked)
+        .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined));
+    match grabbed {
+        Ok(()) => {
+            window.set_cursor_visible(false);
+            true
+        }
+        Err(e) => {
+            log::warn!("Failed to capture the pointer: {}", e);
+            false
+        }
+    }
+}
+
 pub fn gl_draw_program<P: AsRef<Path> + std::fmt::Display>(
     display: &Display<WindowSurface>,
     vertex_shader_path: P,
@@ -0,0 +1,99 @@
+use std::collections::HashSet;
+
+use winit::{
+    event::KeyEvent,
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+/// Modifier keys, either the left or the right one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers {
+        shift: false,
+        control: false,
+        alt: false,
+    };
+
+    /// Whether every modifier of `other` is in `self`.
+    pub fn contains(self, other: Modifiers) -> bool {
+        (self.shift || !other.shift) && (self.control || !other.control) && (self.alt || !other.alt)
+    }
+}
+
+/// Keys held down, tracked from keyboard events so continuous actions can be
+/// applied once per frame instead of once per OS key repeat.
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    held: HashSet<KeyCode>,
+}
+
+impl InputState {
+    /// Record a key press or release. Returns whether it is a new press,
+    /// `false` for releases and key repeats.
+    pub fn on_keyboard_event(&mut self, event: &KeyEvent) -> bool {
+        let PhysicalKey::Code(code) = event.physical_key else {
+            return false;
+        };
+        self.set_held(code, event.state.is_pressed())
+    }
+
+    /// Mark `key` as held or released, returning whether it was just pressed.
+    pub fn set_held(&mut self, key: KeyCode, held: bool) -> bool {
+        if held {
+            self.held.insert(key)
+        } else {
+            self.held.remove(&key);
+            false
+        }
+    }
+
+    pub fn is_held(&self, key: KeyCode) -> bool {
+        self.held.contains(&key)
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        let either = |left, right| self.is_held(left) || self.is_held(right);
+        Modifiers {
+            shift: either(KeyCode::ShiftLeft, KeyCode::ShiftRight),
+            control: either(KeyCode::ControlLeft, KeyCode::ControlRight),
+            alt: either(KeyCode::AltLeft, KeyCode::AltRight),
+        }
+    }
+
+    /// `1` while only `positive` is held, `-1` while only `negative` is, `0`
+    /// otherwise.
+    pub fn axis(&self, negative: KeyCode, positive: KeyCode) -> f32 {
+        self.is_held(positive) as i32 as f32 - self.is_held(negative) as i32 as f32
+    }
+
+    /// Release every key, for when the window loses focus and will not see
+    /// the release events.
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_are_not_new_presses() {
+        let mut input = InputState::default();
+        assert!(input.set_held(KeyCode::KeyW, true));
+        assert!(!input.set_held(KeyCode::KeyW, true));
+        assert_eq!(input.axis(KeyCode::KeyS, KeyCode::KeyW), 1.);
+        input.set_held(KeyCode::KeyS, true);
+        assert_eq!(input.axis(KeyCode::KeyS, KeyCode::KeyW), 0.);
+        assert!(!input.set_held(KeyCode::KeyW, false));
+        assert_eq!(input.axis(KeyCode::KeyS, KeyCode::KeyW), -1.);
+        input.clear();
+        assert!(!input.is_held(KeyCode::KeyS));
+    }
+}
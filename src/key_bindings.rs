@@ -0,0 +1,737 @@
+//! Keys bound to the camera controls, with defaults overridable from a file.
+//!
+//! The file is TOML binding each action to a key name or an array of them,
+//! keys under a `[free]` or `[orbit]` table apply to that camera mode only.
+//! Key names are winit's [`KeyCode`] names, with the `Key`, `Digit` or
+//! `Arrow` prefix optional, led by modifiers such as `Shift+`, `Ctrl+` or
+//! `Alt+`. A bare modifier stands for its left and right keys.
+//!
+//! ```toml
+//! # actions left out keep their default keys
+//! move_forward = "W"
+//! move_backward = "S"
+//! move_up = ["E", "Space"]
+//! move_down = "Q"
+//! reset = "Ctrl+R"
+//! roll_left = []
+//!
+//! [orbit]
+//! turn_left = "Left"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use winit::keyboard::KeyCode;
+
+use crate::{
+    camera::CameraMode,
+    input::{InputState, Modifiers},
+};
+
+/// Something the camera does while a key is held or when it is pressed.
+/// In orbit mode moving forward and backward zooms, the other moves pan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    MoveUp,
+    MoveDown,
+    TurnLeft,
+    TurnRight,
+    TurnUp,
+    TurnDown,
+    RollLeft,
+    RollRight,
+    /// Narrow the field of view while held, see
+    /// [`Camera::zoom_fov`](crate::camera::Camera::zoom_fov).
+    ZoomIn,
+    /// Widen the field of view while held.
+    ZoomOut,
+    /// Move faster while held.
+    Fast,
+    /// Switch between the free and orbit cameras.
+    ToggleMode,
+    /// Switch between the perspective and orthographic projections.
+    ToggleProjection,
+    /// Go back to the view the camera started from.
+    Reset,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::TurnUp,
+        Action::TurnDown,
+        Action::RollLeft,
+        Action::RollRight,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::Fast,
+        Action::ToggleMode,
+        Action::ToggleProjection,
+        Action::Reset,
+    ];
+
+    /// Name of the action in binding files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::StrafeLeft => "strafe_left",
+            Action::StrafeRight => "strafe_right",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::TurnUp => "turn_up",
+            Action::TurnDown => "turn_down",
+            Action::RollLeft => "roll_left",
+            Action::RollRight => "roll_right",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::Fast => "fast",
+            Action::ToggleMode => "toggle_mode",
+            Action::ToggleProjection => "toggle_projection",
+            Action::Reset => "reset",
+        }
+    }
+}
+
+/// A key, with the modifiers that must be held along with it, triggering an
+/// action in one camera mode or, without `mode`, in both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub action: Action,
+    pub key: KeyCode,
+    pub modifiers: Modifiers,
+    pub mode: Option<CameraMode>,
+}
+
+impl Binding {
+    pub fn new(action: Action, key: KeyCode) -> Self {
+        Binding {
+            action,
+            key,
+            modifiers: Modifiers::NONE,
+            mode: None,
+        }
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: CameraMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Whether the binding is for `mode` and its modifiers are held.
+    fn matches(&self, modifiers: Modifiers, mode: CameraMode) -> bool {
+        self.mode.map_or(true, |m| m == mode) && modifiers.contains(self.modifiers)
+    }
+}
+
+#[derive(Debug)]
+pub enum KeyBindingsError {
+    /// The bindings file could not be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The file is not valid TOML, or names an unknown action or key.
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for KeyBindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyBindingsError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            KeyBindingsError::Parse(error) => write!(f, "invalid key bindings: {}", error),
+        }
+    }
+}
+
+impl Error for KeyBindingsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KeyBindingsError::Io { source, .. } => Some(source),
+            KeyBindingsError::Parse(error) => Some(error),
+        }
+    }
+}
+
+/// Table of the keys triggering each [`Action`]. The default table is the
+/// WASD/QE and IJKL/UO layout, with Z and X to zoom, Shift to move faster, C
+/// to switch modes, P to switch projections and R to reset. Turning follows
+/// the free camera's axes in free mode (IK yaw, LJ pitch) and the arrow-like
+/// IJKL around the target in orbit mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    bindings: Vec<Binding>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use Action::*;
+        use KeyCode::*;
+        let mut bindings: Vec<_> = [
+            (MoveUp, KeyW),
+            (MoveDown, KeyS),
+            (StrafeLeft, KeyA),
+            (StrafeRight, KeyD),
+            (MoveForward, KeyQ),
+            (MoveBackward, KeyE),
+            (RollLeft, KeyU),
+            (RollRight, KeyO),
+            (ZoomIn, KeyZ),
+            (ZoomOut, KeyX),
+            (Fast, ShiftLeft),
+            (Fast, ShiftRight),
+            (ToggleMode, KeyC),
+            (ToggleProjection, KeyP),
+            (Reset, KeyR),
+        ]
+        .into_iter()
+        .map(|(action, key)| Binding::new(action, key))
+        .collect();
+        for (mode, keys) in [
+            (CameraMode::Free, [KeyI, KeyK, KeyL, KeyJ]),
+            (CameraMode::Orbit, [KeyL, KeyJ, KeyK, KeyI]),
+        ] {
+            for (action, key) in [TurnLeft, TurnRight, TurnUp, TurnDown]
+                .into_iter()
+                .zip(keys)
+            {
+                bindings.push(Binding::new(action, key).with_mode(mode));
+            }
+        }
+        KeyBindings { bindings }
+    }
+}
+
+impl KeyBindings {
+    /// A table without any binding.
+    pub fn empty() -> Self {
+        KeyBindings {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// Read a bindings file, see the [module documentation](self).
+    pub fn load<P>(path: P) -> Result<Self, KeyBindingsError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| KeyBindingsError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        text.parse()
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    pub fn bind(&mut self, binding: Binding) {
+        if !self.bindings.contains(&binding) {
+            self.bindings.push(binding);
+        }
+    }
+
+    /// Remove the keys of `action` bound to `mode`, or every key of `action`
+    /// without a mode.
+    pub fn unbind(&mut self, action: Action, mode: Option<CameraMode>) {
+        self.bindings
+            .retain(|b| b.action != action || (mode.is_some() && b.mode != mode));
+    }
+
+    /// Whether a key of `action` is held along with its modifiers.
+    pub fn is_active(&self, input: &InputState, mode: CameraMode, action: Action) -> bool {
+        self.bindings
+            .iter()
+            .any(|b| b.action == action && input.is_held(b.key) && self.applies(b, input, mode))
+    }
+
+    /// `1` while only `positive` is active, `-1` while only `negative` is,
+    /// `0` otherwise.
+    pub fn axis(
+        &self,
+        input: &InputState,
+        mode: CameraMode,
+        negative: Action,
+        positive: Action,
+    ) -> f32 {
+        let active = |action| self.is_active(input, mode, action) as i32 as f32;
+        active(positive) - active(negative)
+    }
+
+    /// Actions triggered by pressing `key` with the modifiers held in
+    /// `input`.
+    pub fn triggered<'a>(
+        &'a self,
+        input: &'a InputState,
+        mode: CameraMode,
+        key: KeyCode,
+    ) -> impl Iterator<Item = Action> + 'a {
+        self.bindings
+            .iter()
+            .filter(move |b| b.key == key && self.applies(b, input, mode))
+            .map(|b| b.action)
+    }
+
+    /// Whether `binding` matches the held modifiers and no binding of the
+    /// same key asking for more of them does, so that with `W` and `Shift+W`
+    /// bound, `Shift+W` only triggers the latter while `W` alone still works
+    /// with modifiers nothing else is bound to.
+    fn applies(&self, binding: &Binding, input: &InputState, mode: CameraMode) -> bool {
+        let modifiers = input.modifiers();
+        binding.matches(modifiers, mode)
+            && !self.bindings.iter().any(|b| {
+                b.key == binding.key
+                    && b.modifiers != binding.modifiers
+                    && b.modifiers.contains(binding.modifiers)
+                    && b.matches(modifiers, mode)
+            })
+    }
+}
+
+/// Layout of a bindings file: the actions bound in both modes, and a table
+/// of actions per mode.
+#[derive(Default)]
+struct BindingsFile {
+    both: BTreeMap<Action, Keys>,
+    free: BTreeMap<Action, Keys>,
+    orbit: BTreeMap<Action, Keys>,
+}
+
+/// A top level name, the table of a mode or an action.
+#[derive(Deserialize)]
+#[serde(try_from = "String")]
+enum Name {
+    Mode(CameraMode),
+    Action(Action),
+}
+
+impl TryFrom<String> for Name {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        match name.as_str() {
+            "free" => Ok(Name::Mode(CameraMode::Free)),
+            "orbit" => Ok(Name::Mode(CameraMode::Orbit)),
+            _ => Action::ALL
+                .into_iter()
+                .find(|a| a.name() == name)
+                .map(Name::Action)
+                .ok_or_else(|| format!("unknown action or table `{}`", name)),
+        }
+    }
+}
+
+// Read by hand rather than with `#[serde(flatten)]`, which would lose the
+// position of the errors in the actions.
+impl<'de> Deserialize<'de> for BindingsFile {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FileVisitor;
+
+        impl<'de> Visitor<'de> for FileVisitor {
+            type Value = BindingsFile;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a table of actions")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut file = BindingsFile::default();
+                while let Some(name) = map.next_key()? {
+                    match name {
+                        Name::Mode(CameraMode::Free) => file.free = map.next_value()?,
+                        Name::Mode(CameraMode::Orbit) => file.orbit = map.next_value()?,
+                        Name::Action(action) => {
+                            file.both.insert(action, map.next_value()?);
+                        }
+                    }
+                }
+                Ok(file)
+            }
+        }
+
+        deserializer.deserialize_map(FileVisitor)
+    }
+}
+
+/// A key name or an array of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Keys bound to one action, parsed from their names.
+#[derive(Deserialize)]
+#[serde(try_from = "KeyNames")]
+struct Keys(Vec<(Vec<KeyCode>, Modifiers)>);
+
+impl TryFrom<KeyNames> for Keys {
+    type Error = String;
+
+    fn try_from(names: KeyNames) -> Result<Self, Self::Error> {
+        let names = match names {
+            KeyNames::One(name) => vec![name],
+            KeyNames::Many(names) => names,
+        };
+        names
+            .iter()
+            .map(|name| parse_key(name))
+            .collect::<Result<_, _>>()
+            .map(Keys)
+    }
+}
+
+/// Parse a bindings file on top of the defaults: the keys given for an
+/// action replace its default keys, in both modes at the top level or in one
+/// mode under its table.
+impl FromStr for KeyBindings {
+    type Err = KeyBindingsError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let file: BindingsFile = toml::from_str(text).map_err(KeyBindingsError::Parse)?;
+        let mut table = KeyBindings::default();
+        for (mode, actions) in [
+            (None, file.both),
+            (Some(CameraMode::Free), file.free),
+            (Some(CameraMode::Orbit), file.orbit),
+        ] {
+            for (action, Keys(keys)) in actions {
+                table.unbind(action, mode);
+                for (key_codes, modifiers) in keys {
+                    for key in key_codes {
+                        table.bind(Binding {
+                            action,
+                            key,
+                            modifiers,
+                            mode,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(table)
+    }
+}
+
+/// Keys and modifiers of a name such as `Shift+W`.
+fn parse_key(name: &str) -> Result<(Vec<KeyCode>, Modifiers), String> {
+    let mut parts: Vec<_> = name.split('+').map(str::trim).collect();
+    let key = parts.pop().unwrap_or_default();
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        match parse_modifier(part) {
+            Some((_, modifier)) => {
+                modifiers.shift |= modifier.shift;
+                modifiers.control |= modifier.control;
+                modifiers.alt |= modifier.alt;
+            }
+            None => return Err(format!("unknown modifier {}", part)),
+        }
+    }
+    if let Some((keys, _)) = parse_modifier(key) {
+        return Ok((keys.to_vec(), modifiers));
+    }
+    let code = KEY_CODES
+        .iter()
+        .find(|(code_name, _)| {
+            let short = ["Key", "Digit", "Arrow"]
+                .iter()
+                .find_map(|prefix| code_name.strip_prefix(prefix));
+            code_name.eq_ignore_ascii_case(key)
+                || short.is_some_and(|s| s.eq_ignore_ascii_case(key))
+        })
+        .map(|&(_, code)| code)
+        .ok_or_else(|| format!("unknown key {}", key))?;
+    Ok((vec![code], modifiers))
+}
+
+/// Left and right keys of a modifier name.
+fn parse_modifier(name: &str) -> Option<([KeyCode; 2], Modifiers)> {
+    let mut modifiers = Modifiers::NONE;
+    let keys = match name.to_ascii_lowercase().as_str() {
+        "shift" => {
+            modifiers.shift = true;
+            [KeyCode::ShiftLeft, KeyCode::ShiftRight]
+        }
+        "ctrl" | "control" => {
+            modifiers.control = true;
+            [KeyCode::ControlLeft, KeyCode::ControlRight]
+        }
+        "alt" => {
+            modifiers.alt = true;
+            [KeyCode::AltLeft, KeyCode::AltRight]
+        }
+        _ => return None,
+    };
+    Some((keys, modifiers))
+}
+
+macro_rules! key_codes {
+    ($($code:ident),* $(,)?) => {
+        &[$((stringify!($code), KeyCode::$code)),*]
+    };
+}
+
+/// Keys that can be named in a bindings file.
+const KEY_CODES: &[(&str, KeyCode)] = key_codes![
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyI,
+    KeyJ,
+    KeyK,
+    KeyL,
+    KeyM,
+    KeyN,
+    KeyO,
+    KeyP,
+    KeyQ,
+    KeyR,
+    KeyS,
+    KeyT,
+    KeyU,
+    KeyV,
+    KeyW,
+    KeyX,
+    KeyY,
+    KeyZ,
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    Space,
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    ShiftLeft,
+    ShiftRight,
+    ControlLeft,
+    ControlRight,
+    AltLeft,
+    AltRight,
+    Minus,
+    Equal,
+    Comma,
+    Period,
+    Slash,
+    Semicolon,
+    Quote,
+    BracketLeft,
+    BracketRight,
+    Backquote,
+    Backslash,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(table: &KeyBindings, action: Action) -> Vec<(KeyCode, Option<CameraMode>)> {
+        table
+            .bindings()
+            .iter()
+            .filter(|b| b.action == action)
+            .map(|b| (b.key, b.mode))
+            .collect()
+    }
+
+    #[test]
+    fn file_overrides_the_defaults() {
+        let table: KeyBindings = r#"
+            # comment
+            move_forward = "W"   # trailing comment
+            move_up = ["E", "space"]
+            reset = "Ctrl+R"
+            roll_left = []
+
+            [orbit]
+            turn_left = "Left"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(keys(&table, Action::MoveForward), [(KeyCode::KeyW, None)]);
+        assert_eq!(
+            keys(&table, Action::MoveUp),
+            [(KeyCode::KeyE, None), (KeyCode::Space, None)]
+        );
+        assert!(keys(&table, Action::RollLeft).is_empty());
+        // the free mode default is kept
+        assert_eq!(
+            keys(&table, Action::TurnLeft),
+            [
+                (KeyCode::KeyI, Some(CameraMode::Free)),
+                (KeyCode::ArrowLeft, Some(CameraMode::Orbit))
+            ]
+        );
+        assert_eq!(keys(&table, Action::MoveBackward), [(KeyCode::KeyE, None)]);
+
+        let mut input = InputState::default();
+        input.set_held(KeyCode::KeyR, true);
+        let reset = |input: &InputState| {
+            table
+                .triggered(input, CameraMode::Free, KeyCode::KeyR)
+                .collect::<Vec<_>>()
+        };
+        assert!(reset(&input).is_empty());
+        input.set_held(KeyCode::ControlRight, true);
+        assert_eq!(reset(&input), [Action::Reset]);
+    }
+
+    #[test]
+    fn defaults_bind_turns_per_mode() {
+        let table = KeyBindings::default();
+        let mut input = InputState::default();
+        input.set_held(KeyCode::KeyI, true);
+        input.set_held(KeyCode::ShiftRight, true);
+        let turn = |mode| {
+            Action::ALL
+                .into_iter()
+                .filter(|&a| table.is_active(&input, mode, a))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(turn(CameraMode::Free), [Action::TurnLeft, Action::Fast]);
+        assert_eq!(turn(CameraMode::Orbit), [Action::TurnDown, Action::Fast]);
+    }
+
+    #[test]
+    fn the_most_specific_modifiers_win() {
+        let table: KeyBindings = r#"
+            move_up = "W"
+            move_forward = "Shift+W"
+        "#
+        .parse()
+        .unwrap();
+        let mut input = InputState::default();
+        input.set_held(KeyCode::KeyW, true);
+        let active = |input: &InputState| {
+            Action::ALL
+                .into_iter()
+                .filter(|&a| table.is_active(input, CameraMode::Free, a))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(active(&input), [Action::MoveUp]);
+        input.set_held(KeyCode::ShiftLeft, true);
+        assert_eq!(active(&input), [Action::MoveForward, Action::Fast]);
+        // other modifiers still let the plain binding through
+        input.set_held(KeyCode::ShiftLeft, false);
+        input.set_held(KeyCode::AltLeft, true);
+        assert_eq!(active(&input), [Action::MoveUp]);
+        assert_eq!(
+            table
+                .triggered(&input, CameraMode::Free, KeyCode::KeyW)
+                .collect::<Vec<_>>(),
+            [Action::MoveUp]
+        );
+    }
+
+    #[test]
+    fn errors_report_the_line() {
+        for (text, line) in [
+            ("move_up = \"W\"\njump = \"Space\"", 2),
+            ("move_up = \"Hyper+W\"", 1),
+            ("\n\nmove_up = \"Nope\"", 3),
+            ("move_up = W", 1),
+            ("[walk]", 1),
+            ("move_up = [\"W\"", 1),
+        ] {
+            match text.parse::<KeyBindings>() {
+                Err(error @ KeyBindingsError::Parse(_)) => {
+                    let message = error.to_string();
+                    assert!(message.contains(&format!("line {}", line)), "{}", message);
+                }
+                other => panic!("{}: {:?}", text, other),
+            }
+        }
+        assert!(matches!(
+            KeyBindings::load("/nonexistent/bindings.toml"),
+            Err(KeyBindingsError::Io { .. })
+        ));
+    }
+}
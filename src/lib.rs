@@ -1,6 +1,22 @@
+pub mod asset_loader;
+pub mod bounds;
 pub mod camera;
+pub mod gltf_loader;
 pub mod helper;
+pub mod input;
+pub mod key_bindings;
+pub mod mesh;
+pub mod mesh_cache;
+pub mod obj_exporter;
 pub mod obj_loader;
+pub mod ply_exporter;
+pub mod ply_loader;
+pub mod primitives;
+pub mod stl_loader;
+pub mod upload;
+
+#[cfg(test)]
+mod test_util;
 
 pub fn add(left: usize, right: usize) -> usize {
     left + right
@@ -0,0 +1,358 @@
+use std::{fmt, ops::Range, path::PathBuf, time::Duration};
+
+use glam::{Vec2, Vec3};
+use glium::implement_vertex;
+
+mod normals;
+mod optimize;
+mod simplify;
+mod weld;
+
+pub use normals::{NormalMode, NormalWeighting};
+pub use optimize::{
+    analyze_vertex_cache, optimize_overdraw, optimize_vertex_cache, optimize_vertex_fetch,
+    CacheStats, OptimizeOptions,
+};
+pub use simplify::{simplify, Lod, LodChain, LodOptions};
+pub use weld::{WeldOptions, WeldReport};
+
+/// Vertex layout shared by every mesh the crate loads or generates.
+///
+/// `tangent.w` holds the handedness of the tangent frame, `bitangent` is
+/// `cross(normal, tangent.xyz) * tangent.w` as in MikkTSpace.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub tangent: [f32; 4],
+    pub bitangent: [f32; 3],
+    /// linear RGBA, white unless the source file has vertex colours
+    pub color: [f32; 4],
+}
+
+implement_vertex!(Vertex, position, normal, tex_coords, tangent, bitangent, color);
+
+impl Default for Vertex {
+    fn default() -> Self {
+        Vertex {
+            position: [0.; 3],
+            normal: [0.; 3],
+            tex_coords: [0.; 2],
+            tangent: [0.; 4],
+            bitangent: [0.; 3],
+            color: [1.; 4],
+        }
+    }
+}
+
+impl Vertex {
+    pub fn new(position: [f32; 3], normal: [f32; 3]) -> Self {
+        Vertex {
+            position,
+            normal,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_tex_coords(mut self, tex_coords: [f32; 2]) -> Self {
+        self.tex_coords = tex_coords;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Which of the optional [`Vertex`] attributes carry real data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VertexAttributes {
+    pub normals: bool,
+    pub tex_coords: bool,
+    pub tangents: bool,
+    pub colors: bool,
+}
+
+/// Indexed triangle list ready to be uploaded as a vertex and an index buffer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub attributes: VertexAttributes,
+}
+
+impl Mesh {
+    /// Cube spanning -1 to 1 on every axis with flat normals, used in place
+    /// of assets that are missing from the checkout.
+    pub fn placeholder() -> Self {
+        crate::primitives::cube(2.)
+    }
+
+    /// Append the vertices and indices of `other`. An optional attribute stays
+    /// present only if both meshes have it, except tangents which are
+    /// generated for the mesh lacking them.
+    pub fn append(&mut self, mut other: Mesh) {
+        if self.vertices.is_empty() {
+            self.attributes = other.attributes;
+        } else {
+            if self.attributes.tangents && !other.attributes.tangents {
+                other.generate_tangents();
+            } else if other.attributes.tangents && !self.attributes.tangents {
+                self.generate_tangents();
+            }
+            let (a, b) = (&mut self.attributes, other.attributes);
+            a.normals &= b.normals;
+            a.tex_coords &= b.tex_coords;
+            a.tangents &= b.tangents;
+            a.colors &= b.colors;
+        }
+        let offset = self.vertices.len() as u32;
+        self.indices
+            .extend(other.indices.iter().map(|i| i + offset));
+        self.vertices.extend(other.vertices);
+    }
+
+    /// Generate per-vertex tangents and bitangents from the texture
+    /// coordinates, following the MikkTSpace conventions: face tangents are
+    /// projected onto each vertex normal, weighted by the corner angle and
+    /// orthonormalized. Vertices without usable UVs get an arbitrary tangent
+    /// perpendicular to their normal.
+    pub fn generate_tangents(&mut self) {
+        let n = self.vertices.len();
+        let mut tangents = vec![Vec3::ZERO; n];
+        let mut bitangents = vec![Vec3::ZERO; n];
+
+        if self.attributes.tex_coords {
+            for tri in self.indices.chunks_exact(3) {
+                let idx = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+                let p = idx.map(|i| Vec3::from(self.vertices[i].position));
+                let uv = idx.map(|i| Vec2::from(self.vertices[i].tex_coords));
+
+                let (e1, e2) = (p[1] - p[0], p[2] - p[0]);
+                let (d1, d2) = (uv[1] - uv[0], uv[2] - uv[0]);
+                let det = d1.x * d2.y - d2.x * d1.y;
+                if det.abs() < f32::EPSILON {
+                    continue;
+                }
+                let t = (e1 * d2.y - e2 * d1.y) / det;
+                let b = (e2 * d1.x - e1 * d2.x) / det;
+
+                for corner in 0..3 {
+                    let i = idx[corner];
+                    let normal = Vec3::from(self.vertices[i].normal);
+                    let a = p[(corner + 1) % 3] - p[corner];
+                    let c = p[(corner + 2) % 3] - p[corner];
+                    let angle = a.angle_between(c);
+                    if !angle.is_finite() {
+                        continue;
+                    }
+                    tangents[i] += (t - normal * normal.dot(t)).normalize_or_zero() * angle;
+                    bitangents[i] += (b - normal * normal.dot(b)).normalize_or_zero() * angle;
+                }
+            }
+        }
+
+        for (i, vertex) in self.vertices.iter_mut().enumerate() {
+            let normal = Vec3::from(vertex.normal).normalize_or_zero();
+            let t = (tangents[i] - normal * normal.dot(tangents[i])).normalize_or_zero();
+            let t = if t == Vec3::ZERO {
+                normal.any_orthonormal_vector()
+            } else {
+                t
+            };
+            let w = if normal.cross(t).dot(bitangents[i]) < 0. {
+                -1.
+            } else {
+                1.
+            };
+            vertex.tangent = t.extend(w).to_array();
+            vertex.bitangent = (normal.cross(t) * w).to_array();
+        }
+        self.attributes.tangents = true;
+    }
+}
+
+/// Surface description parsed from an MTL library.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: String,
+    /// `Ka`
+    pub ambient: [f32; 3],
+    /// `Kd`
+    pub diffuse: [f32; 3],
+    /// `Ks`
+    pub specular: [f32; 3],
+    /// `Ns`
+    pub shininess: f32,
+    /// `d`, 1 is fully opaque
+    pub dissolve: f32,
+    /// glTF `metallicFactor`
+    pub metallic: f32,
+    /// glTF `roughnessFactor`
+    pub roughness: f32,
+    pub diffuse_texture: Option<PathBuf>,
+    pub normal_texture: Option<PathBuf>,
+    pub specular_texture: Option<PathBuf>,
+    pub metallic_roughness_texture: Option<PathBuf>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: String::new(),
+            ambient: [0.; 3],
+            diffuse: [0.8, 0.7, 0.6],
+            specular: [0.; 3],
+            shininess: 0.,
+            dissolve: 1.,
+            metallic: 0.,
+            roughness: 1.,
+            diffuse_texture: None,
+            normal_texture: None,
+            specular_texture: None,
+            metallic_roughness_texture: None,
+        }
+    }
+}
+
+/// A range of a [`Model`]'s index buffer drawn with a single material.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubMesh {
+    pub name: String,
+    pub indices: Range<u32>,
+    /// Index into [`Model::materials`].
+    pub material: Option<usize>,
+}
+
+/// A mesh split into submeshes that each reference one of the materials.
+#[derive(Clone, Debug, Default)]
+pub struct Model {
+    pub mesh: Mesh,
+    pub submeshes: Vec<SubMesh>,
+    pub materials: Vec<Material>,
+}
+
+/// A single submesh covering the whole mesh, with the default material.
+impl From<Mesh> for Model {
+    fn from(mesh: Mesh) -> Self {
+        Model {
+            submeshes: vec![SubMesh {
+                name: String::new(),
+                indices: 0..mesh.indices.len() as u32,
+                material: None,
+            }],
+            mesh,
+            materials: Vec::new(),
+        }
+    }
+}
+
+impl Model {
+    /// [`Mesh::placeholder`] as a single submesh with the default material.
+    pub fn placeholder() -> Self {
+        let mut model = Model::from(Mesh::placeholder());
+        model.submeshes[0].name = "placeholder".to_owned();
+        model
+    }
+
+    pub fn material(&self, submesh: &SubMesh) -> Option<&Material> {
+        submesh.material.and_then(|i| self.materials.get(i))
+    }
+
+    pub fn submesh_indices(&self, submesh: &SubMesh) -> &[u32] {
+        &self.mesh.indices[submesh.indices.start as usize..submesh.indices.end as usize]
+    }
+}
+
+/// Size of a loaded model and how long loading it took, logged by the loaders.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoadStats {
+    pub vertices: usize,
+    pub indices: usize,
+    pub submeshes: usize,
+    pub materials: usize,
+    pub elapsed: Duration,
+}
+
+impl LoadStats {
+    pub fn new(model: &Model, elapsed: Duration) -> Self {
+        LoadStats {
+            vertices: model.mesh.vertices.len(),
+            indices: model.mesh.indices.len(),
+            submeshes: model.submeshes.len(),
+            materials: model.materials.len(),
+            elapsed,
+        }
+    }
+
+    /// Stats of a bare mesh, one submesh without material.
+    pub fn of_mesh(mesh: &Mesh, elapsed: Duration) -> Self {
+        LoadStats {
+            vertices: mesh.vertices.len(),
+            indices: mesh.indices.len(),
+            submeshes: 1,
+            materials: 0,
+            elapsed,
+        }
+    }
+}
+
+impl fmt::Display for LoadStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} vertices, {} indices, {} submeshes, {} materials in {:.1?}",
+            self.vertices, self.indices, self.submeshes, self.materials, self.elapsed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholder_faces_point_outwards() {
+        let mesh = Mesh::placeholder();
+        assert_eq!(mesh.indices.len(), 36);
+        for tri in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(mesh.vertices[tri[i] as usize].position));
+            let n = Vec3::from(mesh.vertices[tri[0] as usize].normal);
+            assert!((b - a).cross(c - a).dot(n) > 0.);
+        }
+    }
+
+    #[test]
+    fn tangents_follow_uv_directions() {
+        let mut mesh = Mesh {
+            vertices: vec![
+                Vertex::new([0., 0., 0.], [0., 0., 1.]).with_tex_coords([0., 0.]),
+                Vertex::new([2., 0., 0.], [0., 0., 1.]).with_tex_coords([1., 0.]),
+                Vertex::new([0., 2., 0.], [0., 0., 1.]).with_tex_coords([0., 1.]),
+            ],
+            indices: vec![0, 1, 2],
+            attributes: VertexAttributes {
+                normals: true,
+                tex_coords: true,
+                ..Default::default()
+            },
+        };
+        mesh.generate_tangents();
+        for v in &mesh.vertices {
+            assert_eq!(v.tangent, [1., 0., 0., 1.]);
+            assert_eq!(v.bitangent, [0., 1., 0.]);
+        }
+
+        // mirrored UVs flip the handedness
+        for v in &mut mesh.vertices {
+            v.tex_coords[1] = -v.tex_coords[1];
+        }
+        mesh.generate_tangents();
+        for v in &mesh.vertices {
+            assert_eq!(v.tangent, [1., 0., 0., -1.]);
+            assert_eq!(v.bitangent, [0., -1., 0.]);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+
+use super::{Mesh, Vertex};
+
+/// How [`Mesh::generate_normals`] fills in vertex normals.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NormalMode {
+    /// Every triangle gets its own three vertices carrying the face normal.
+    Flat,
+    /// Face normals are averaged into the vertices sharing a position. Corners
+    /// whose faces meet at more than `crease_angle` degrees are split into
+    /// separate vertices so hard edges stay sharp.
+    Smooth {
+        weighting: NormalWeighting,
+        crease_angle: f32,
+    },
+}
+
+impl Default for NormalMode {
+    fn default() -> Self {
+        NormalMode::Smooth {
+            weighting: NormalWeighting::Angle,
+            crease_angle: 180.,
+        }
+    }
+}
+
+/// How much each face contributes to the smooth normal of its corners.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// Proportional to the triangle area, big faces dominate.
+    Area,
+    /// Proportional to the corner angle, independent of tessellation.
+    #[default]
+    Angle,
+}
+
+impl Mesh {
+    /// Replace the vertex normals, duplicating vertices where a single normal
+    /// per vertex cannot represent the requested shading. Tangents are
+    /// regenerated if the mesh had them.
+    pub fn generate_normals(&mut self, mode: NormalMode) {
+        match mode {
+            NormalMode::Flat => self.flat_normals(),
+            NormalMode::Smooth {
+                weighting,
+                crease_angle,
+            } => self.smooth_normals(weighting, crease_angle),
+        }
+        self.attributes.normals = true;
+        if self.attributes.tangents {
+            self.generate_tangents();
+        }
+    }
+
+    fn flat_normals(&mut self) {
+        let mut vertices = Vec::with_capacity(self.indices.len());
+        for tri in self.indices.chunks_exact(3) {
+            let corners = [0, 1, 2].map(|i| self.vertices[tri[i] as usize]);
+            let normal = face_normal(&corners.map(|v| Vec3::from(v.position))).to_array();
+            vertices.extend(corners.map(|v| Vertex { normal, ..v }));
+        }
+        self.indices = (0..vertices.len() as u32).collect();
+        self.vertices = vertices;
+    }
+
+    fn smooth_normals(&mut self, weighting: NormalWeighting, crease_angle: f32) {
+        let triangles = self.indices.len() / 3;
+        let position = |i: u32| Vec3::from(self.vertices[i as usize].position);
+
+        let mut face_normals = Vec::with_capacity(triangles);
+        // contribution of each face to each of its corners
+        let mut weights = Vec::with_capacity(triangles * 3);
+        for tri in self.indices.chunks_exact(3) {
+            let p = [0, 1, 2].map(|i| position(tri[i]));
+            let normal = face_normal(&p);
+            let area = (p[1] - p[0]).cross(p[2] - p[0]).length() / 2.;
+            face_normals.push(normal);
+            for corner in 0..3 {
+                weights.push(match weighting {
+                    NormalWeighting::Area => area,
+                    NormalWeighting::Angle => {
+                        let a = p[(corner + 1) % 3] - p[corner];
+                        let b = p[(corner + 2) % 3] - p[corner];
+                        let angle = a.angle_between(b);
+                        if angle.is_finite() {
+                            angle
+                        } else {
+                            0.
+                        }
+                    }
+                });
+            }
+        }
+
+        // corners grouped by position, so UV seams do not show up as shading seams
+        let mut groups: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+        for (corner, &i) in self.indices.iter().enumerate() {
+            let key = self.vertices[i as usize].position.map(f32::to_bits);
+            groups.entry(key).or_default().push(corner);
+        }
+
+        let cos_crease = crease_angle.to_radians().cos();
+        let smooth_all = crease_angle >= 180.;
+        let mut corner_normals = vec![Vec3::ZERO; self.indices.len()];
+        for corners in groups.values() {
+            if smooth_all {
+                let normal = corners
+                    .iter()
+                    .map(|&c| face_normals[c / 3] * weights[c])
+                    .sum::<Vec3>()
+                    .normalize_or_zero();
+                corners.iter().for_each(|&c| corner_normals[c] = normal);
+                continue;
+            }
+            for &c in corners {
+                let own = face_normals[c / 3];
+                corner_normals[c] = corners
+                    .iter()
+                    .filter(|&&o| face_normals[o / 3].dot(own) >= cos_crease)
+                    .map(|&o| face_normals[o / 3] * weights[o])
+                    .sum::<Vec3>()
+                    .normalize_or_zero();
+            }
+        }
+
+        // a vertex referenced with different normals is split
+        let mut assigned: Vec<Option<Vec3>> = vec![None; self.vertices.len()];
+        let mut splits: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+        for (corner, normal) in corner_normals.into_iter().enumerate() {
+            let i = self.indices[corner];
+            match assigned[i as usize] {
+                None => {
+                    assigned[i as usize] = Some(normal);
+                    self.vertices[i as usize].normal = normal.to_array();
+                }
+                Some(n) if n.abs_diff_eq(normal, 1e-5) => {}
+                Some(_) => {
+                    let key = (i, normal.to_array().map(f32::to_bits));
+                    let vertices = &mut self.vertices;
+                    self.indices[corner] = *splits.entry(key).or_insert_with(|| {
+                        vertices.push(Vertex {
+                            normal: normal.to_array(),
+                            ..vertices[i as usize]
+                        });
+                        vertices.len() as u32 - 1
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn face_normal(p: &[Vec3; 3]) -> Vec3 {
+    (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::VertexAttributes;
+
+    /// Two triangles folded along the shared edge x = 0 at a right angle.
+    fn hinge() -> Mesh {
+        let p = [[0., 0., 0.], [0., 1., 0.], [1., 0., 0.], [0., 0., 1.]];
+        Mesh {
+            vertices: p.iter().map(|&p| Vertex::new(p, [0.; 3])).collect(),
+            indices: vec![0, 1, 2, 0, 3, 1],
+            attributes: VertexAttributes::default(),
+        }
+    }
+
+    #[test]
+    fn smooth_normals_average_the_hinge() {
+        let mut mesh = hinge();
+        mesh.generate_normals(NormalMode::default());
+        assert_eq!(mesh.vertices.len(), 4);
+        let n = Vec3::from(mesh.vertices[0].normal);
+        assert!(n.abs_diff_eq(Vec3::new(-1., 0., -1.).normalize(), 1e-6));
+        assert_eq!(mesh.vertices[2].normal, [0., 0., -1.]);
+    }
+
+    #[test]
+    fn crease_angle_splits_shared_vertices() {
+        let mut mesh = hinge();
+        mesh.generate_normals(NormalMode::Smooth {
+            weighting: NormalWeighting::Area,
+            crease_angle: 60.,
+        });
+        assert_eq!(mesh.vertices.len(), 6);
+        for tri in mesh.indices.chunks(3) {
+            let n = mesh.vertices[tri[0] as usize].normal;
+            assert!(tri.iter().all(|&i| mesh.vertices[i as usize].normal == n));
+        }
+    }
+
+    #[test]
+    fn flat_normals_unweld_every_triangle() {
+        let mut mesh = hinge();
+        mesh.generate_normals(NormalMode::Flat);
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.vertices[3].normal, [-1., 0., 0.]);
+        assert!(mesh.attributes.normals);
+    }
+}
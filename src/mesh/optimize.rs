@@ -0,0 +1,454 @@
+use std::ops::Range;
+
+use glam::Vec3;
+
+use super::{Mesh, Model, Vertex};
+
+/// Post-transform cache size the Forsyth scoring is tuned for, also the FIFO
+/// the overdraw pass clusters and checks the ACMR with.
+const CACHE_SIZE: usize = 32;
+
+/// Settings for [`Mesh::optimize`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptimizeOptions {
+    /// Reorder triangle clusters front to back. The new order is kept only if
+    /// its ACMR is at most `threshold` times the cache optimized one.
+    pub overdraw_threshold: Option<f32>,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        OptimizeOptions {
+            overdraw_threshold: Some(1.05),
+        }
+    }
+}
+
+/// Result of simulating a FIFO post-transform vertex cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    /// Vertices transformed.
+    pub misses: usize,
+    /// Average cache miss ratio, misses per triangle. 0.5 is the best a
+    /// regular grid can reach, 3 means no reuse at all.
+    pub acmr: f32,
+    /// Average transform to vertex ratio, misses per referenced vertex. 1 is
+    /// optimal.
+    pub atvr: f32,
+}
+
+/// Simulate a FIFO cache of `cache_size` entries over a triangle list.
+pub fn analyze_vertex_cache(indices: &[u32], vertex_count: usize, cache_size: usize) -> CacheStats {
+    let mut timestamps = vec![0_usize; vertex_count];
+    let mut time = cache_size + 1;
+    let mut misses = 0;
+    for &i in indices {
+        // a vertex is cached if it entered the FIFO less than cache_size misses ago
+        if time - timestamps[i as usize] > cache_size {
+            timestamps[i as usize] = time;
+            time += 1;
+            misses += 1;
+        }
+    }
+    let referenced = timestamps.iter().filter(|&&t| t != 0).count();
+    CacheStats {
+        misses,
+        acmr: misses as f32 / (indices.len() / 3).max(1) as f32,
+        atvr: misses as f32 / referenced.max(1) as f32,
+    }
+}
+
+/// Reorder triangles for the post-transform vertex cache using Tom Forsyth's
+/// "Linear-speed vertex cache optimisation".
+pub fn optimize_vertex_cache(indices: &mut [u32], vertex_count: usize) {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return;
+    }
+
+    // vertex -> triangles, as offsets into one flat list
+    let mut offsets = vec![0_usize; vertex_count + 1];
+    for &i in indices.iter() {
+        offsets[i as usize + 1] += 1;
+    }
+    for v in 0..vertex_count {
+        offsets[v + 1] += offsets[v];
+    }
+    let mut adjacency = vec![0_u32; indices.len()];
+    let mut fill = offsets.clone();
+    for (t, tri) in indices.chunks_exact(3).enumerate() {
+        for &i in tri {
+            adjacency[fill[i as usize]] = t as u32;
+            fill[i as usize] += 1;
+        }
+    }
+
+    let mut remaining = (0..vertex_count)
+        .map(|v| offsets[v + 1] - offsets[v])
+        .collect::<Vec<_>>();
+    let mut cache_position = vec![-1_i32; vertex_count];
+    let mut vertex_scores = (0..vertex_count)
+        .map(|v| vertex_score(-1, remaining[v]))
+        .collect::<Vec<_>>();
+    let mut emitted = vec![false; triangle_count];
+    let mut triangle_scores = indices
+        .chunks_exact(3)
+        .map(|tri| tri.iter().map(|&i| vertex_scores[i as usize]).sum::<f32>())
+        .collect::<Vec<_>>();
+
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut output = Vec::with_capacity(indices.len());
+    // vertices of the emitted triangles, to restart from when the cache has
+    // no triangle left
+    let mut dead_end = Vec::new();
+    let mut next_unemitted = 0;
+    let mut best =
+        (0..triangle_count).max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+    while let Some(t) = best {
+        let tri = [0, 1, 2].map(|k| indices[3 * t + k]);
+        output.extend(tri);
+        emitted[t] = true;
+        dead_end.extend(tri);
+
+        for &v in &tri {
+            let v = v as usize;
+            remaining[v] -= 1;
+            // drop the triangle from the vertex's live adjacency
+            let live = &mut adjacency[offsets[v]..offsets[v] + remaining[v] + 1];
+            if let Some(k) = live.iter().position(|&a| a as usize == t) {
+                live.swap(k, remaining[v]);
+            }
+        }
+
+        // move the triangle's vertices to the front of the LRU cache
+        let mut new_cache = tri.to_vec();
+        new_cache.extend(cache.iter().filter(|v| !tri.contains(v)));
+        for (position, &v) in new_cache.iter().enumerate() {
+            cache_position[v as usize] = if position < CACHE_SIZE {
+                position as i32
+            } else {
+                -1
+            };
+        }
+
+        // rescore the vertices that moved and the triangles that use them
+        best = None;
+        let mut best_score = -1.;
+        for &v in &new_cache {
+            let v = v as usize;
+            vertex_scores[v] = vertex_score(cache_position[v], remaining[v]);
+        }
+        for &v in &new_cache {
+            let v = v as usize;
+            for &a in &adjacency[offsets[v]..offsets[v] + remaining[v]] {
+                let a = a as usize;
+                let score = (0..3)
+                    .map(|k| vertex_scores[indices[3 * a + k] as usize])
+                    .sum();
+                triangle_scores[a] = score;
+                if score > best_score {
+                    best_score = score;
+                    best = Some(a);
+                }
+            }
+        }
+        new_cache.truncate(CACHE_SIZE);
+        cache = new_cache;
+
+        // rescanning every triangle here would make meshes without shared
+        // vertices quadratic, so continue from the most recent vertex with
+        // triangles left, or else from the next triangle in the input
+        while best.is_none() {
+            let Some(v) = dead_end.pop() else {
+                break;
+            };
+            let v = v as usize;
+            best = adjacency[offsets[v]..offsets[v] + remaining[v]]
+                .iter()
+                .map(|&a| a as usize)
+                .max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+        }
+        if best.is_none() {
+            while next_unemitted < triangle_count && emitted[next_unemitted] {
+                next_unemitted += 1;
+            }
+            if next_unemitted < triangle_count {
+                best = Some(next_unemitted);
+            }
+        }
+    }
+
+    output.extend_from_slice(&indices[triangle_count * 3..]);
+    indices.copy_from_slice(&output);
+}
+
+fn vertex_score(cache_position: i32, remaining: usize) -> f32 {
+    const DECAY_POWER: f32 = 1.5;
+    const LAST_TRIANGLE_SCORE: f32 = 0.75;
+    const VALENCE_BOOST_SCALE: f32 = 2.;
+    const VALENCE_BOOST_POWER: f32 = 0.5;
+
+    if remaining == 0 {
+        return -1.;
+    }
+    let cache_score = match cache_position {
+        p if p < 0 => 0.,
+        // the most recent triangle should not be favoured too much
+        p if p < 3 => LAST_TRIANGLE_SCORE,
+        p => {
+            let scale = 1. / (CACHE_SIZE - 3) as f32;
+            (1. - (p - 3) as f32 * scale).powf(DECAY_POWER)
+        }
+    };
+    // vertices with few triangles left are finished off first
+    cache_score + VALENCE_BOOST_SCALE * (remaining as f32).powf(-VALENCE_BOOST_POWER)
+}
+
+/// Reorder clusters of cache optimized triangles so that the ones facing away
+/// from the mesh centre, which tend to occlude the rest, are drawn first.
+/// Clusters break where the FIFO cache misses all three vertices, which keeps
+/// the cache behaviour mostly intact; the order is reverted if the ACMR grows
+/// past `threshold` times the original.
+pub fn optimize_overdraw(indices: &mut [u32], vertices: &[Vertex], threshold: f32) {
+    let triangle_count = indices.len() / 3;
+    if triangle_count < 2 {
+        return;
+    }
+    let before = analyze_vertex_cache(indices, vertices.len(), CACHE_SIZE);
+
+    let mut clusters = Vec::new();
+    let mut timestamps = vec![0_usize; vertices.len()];
+    let mut time = CACHE_SIZE + 1;
+    for (t, tri) in indices.chunks_exact(3).enumerate() {
+        let mut misses = 0;
+        for &i in tri {
+            if time - timestamps[i as usize] > CACHE_SIZE {
+                timestamps[i as usize] = time;
+                time += 1;
+                misses += 1;
+            }
+        }
+        if misses == 3 || t == 0 {
+            clusters.push(t);
+        }
+    }
+    clusters.push(triangle_count);
+
+    let position = |i: u32| Vec3::from(vertices[i as usize].position);
+    let centroid = |range: Range<usize>| {
+        let (mut sum, mut area) = (Vec3::ZERO, 0.);
+        for tri in indices[range.start * 3..range.end * 3].chunks_exact(3) {
+            let p = [0, 1, 2].map(|k| position(tri[k]));
+            let a = (p[1] - p[0]).cross(p[2] - p[0]).length();
+            sum += (p[0] + p[1] + p[2]) / 3. * a;
+            area += a;
+        }
+        (sum, area)
+    };
+    let (sum, area) = centroid(0..triangle_count);
+    let mesh_centre = if area > 0. { sum / area } else { Vec3::ZERO };
+
+    let mut order = clusters
+        .windows(2)
+        .map(|w| {
+            let (sum, area) = centroid(w[0]..w[1]);
+            let normal = indices[w[0] * 3..w[1] * 3]
+                .chunks_exact(3)
+                .map(|tri| {
+                    let p = [0, 1, 2].map(|k| position(tri[k]));
+                    (p[1] - p[0]).cross(p[2] - p[0])
+                })
+                .sum::<Vec3>()
+                .normalize_or_zero();
+            let centre = if area > 0. { sum / area } else { mesh_centre };
+            ((centre - mesh_centre).dot(normal), w[0]..w[1])
+        })
+        .collect::<Vec<_>>();
+    order.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let reordered = order
+        .iter()
+        .flat_map(|(_, range)| indices[range.start * 3..range.end * 3].iter().copied())
+        .collect::<Vec<_>>();
+    let after = analyze_vertex_cache(&reordered, vertices.len(), CACHE_SIZE);
+    if after.acmr <= before.acmr * threshold {
+        indices[..reordered.len()].copy_from_slice(&reordered);
+    }
+}
+
+/// Reorder vertices in the order the index buffer first uses them, so the
+/// vertex fetch walks memory linearly. Unreferenced vertices move to the end.
+pub fn optimize_vertex_fetch(vertices: &mut Vec<Vertex>, indices: &mut [u32]) {
+    let mut remap = vec![u32::MAX; vertices.len()];
+    let mut reordered = Vec::with_capacity(vertices.len());
+    for i in indices.iter_mut() {
+        if remap[*i as usize] == u32::MAX {
+            remap[*i as usize] = reordered.len() as u32;
+            reordered.push(vertices[*i as usize]);
+        }
+        *i = remap[*i as usize];
+    }
+    reordered.extend(
+        vertices
+            .iter()
+            .zip(&remap)
+            .filter(|(_, &r)| r == u32::MAX)
+            .map(|(v, _)| *v),
+    );
+    *vertices = reordered;
+}
+
+impl Mesh {
+    /// Vertex cache, then overdraw, then vertex fetch optimisation.
+    pub fn optimize(&mut self, options: &OptimizeOptions) {
+        let whole = 0..self.indices.len() as u32;
+        self.optimize_ranges(options, &[whole]);
+    }
+
+    pub fn cache_stats(&self, cache_size: usize) -> CacheStats {
+        analyze_vertex_cache(&self.indices, self.vertices.len(), cache_size)
+    }
+
+    fn optimize_ranges(&mut self, options: &OptimizeOptions, ranges: &[Range<u32>]) {
+        for range in ranges {
+            let indices = &mut self.indices[range.start as usize..range.end as usize];
+            optimize_vertex_cache(indices, self.vertices.len());
+            if let Some(threshold) = options.overdraw_threshold {
+                optimize_overdraw(indices, &self.vertices, threshold);
+            }
+        }
+        optimize_vertex_fetch(&mut self.vertices, &mut self.indices);
+    }
+}
+
+impl Model {
+    /// [`Mesh::optimize`] applied to every submesh on its own, so the submesh
+    /// ranges stay valid.
+    pub fn optimize(&mut self, options: &OptimizeOptions) {
+        let ranges = self
+            .submeshes
+            .iter()
+            .map(|s| s.indices.clone())
+            .collect::<Vec<_>>();
+        self.mesh.optimize_ranges(options, &ranges);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::VertexAttributes;
+
+    /// `n` x `n` quads with the triangles in a scrambled order.
+    fn scrambled_grid(n: u32) -> Mesh {
+        let vertices = (0..=n)
+            .flat_map(|y| (0..=n).map(move |x| Vertex::new([x as f32, y as f32, 0.], [0., 0., 1.])))
+            .collect();
+        let mut triangles = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                triangles.push([i, i + 1, i + n + 2]);
+                triangles.push([i, i + n + 2, i + n + 1]);
+            }
+        }
+        // deterministic shuffle
+        let len = triangles.len();
+        for k in 0..len {
+            triangles.swap(k, (k * 7919 + 13) % len);
+        }
+        Mesh {
+            vertices,
+            indices: triangles.concat(),
+            attributes: VertexAttributes {
+                normals: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn sorted_triangles(indices: &[u32], vertices: &[Vertex]) -> Vec<[[u32; 3]; 3]> {
+        let mut tris = indices
+            .chunks(3)
+            .map(|t| {
+                let mut t = [0, 1, 2].map(|k| vertices[t[k] as usize].position.map(f32::to_bits));
+                let first = (0..3).min_by_key(|&k| t[k]).unwrap();
+                t.rotate_left(first);
+                t
+            })
+            .collect::<Vec<_>>();
+        tris.sort();
+        tris
+    }
+
+    #[test]
+    fn cache_stats_of_a_strip() {
+        let indices = [0, 1, 2, 2, 1, 3, 2, 3, 4];
+        let stats = analyze_vertex_cache(&indices, 5, 16);
+        assert_eq!(stats.misses, 5);
+        assert!((stats.acmr - 5. / 3.).abs() < 1e-6);
+        assert_eq!(stats.atvr, 1.);
+
+        // a cache of 1 entry only reuses immediate repeats
+        assert_eq!(analyze_vertex_cache(&indices, 5, 1).misses, 8);
+    }
+
+    #[test]
+    fn optimisation_lowers_acmr_and_keeps_triangles() {
+        let mut mesh = scrambled_grid(40);
+        let original = sorted_triangles(&mesh.indices, &mesh.vertices);
+        let before = mesh.cache_stats(16);
+
+        mesh.optimize(&OptimizeOptions::default());
+        let after = mesh.cache_stats(16);
+        assert!(
+            after.acmr < before.acmr * 0.5,
+            "{:?} -> {:?}",
+            before,
+            after
+        );
+        assert!(after.acmr < 0.8, "{:?}", after);
+        assert_eq!(sorted_triangles(&mesh.indices, &mesh.vertices), original);
+
+        // vertex fetch order follows the index buffer
+        let mut seen = 0;
+        for &i in &mesh.indices {
+            assert!(i <= seen);
+            seen = seen.max(i + 1);
+        }
+    }
+
+    #[test]
+    fn triangle_soups_are_optimised_in_linear_time() {
+        // no shared vertices, as with flat normals or STL files
+        let count = 200_000;
+        let mut indices = (0..count * 3).collect::<Vec<u32>>();
+        let start = std::time::Instant::now();
+        optimize_vertex_cache(&mut indices, count as usize * 3);
+        assert!(start.elapsed().as_secs() < 10, "{:?}", start.elapsed());
+        let mut triangles = indices.chunks(3).map(|t| t[0]).collect::<Vec<_>>();
+        triangles.sort();
+        assert!(triangles.into_iter().eq((0..count).map(|t| t * 3)));
+    }
+
+    #[test]
+    fn model_submeshes_stay_in_their_ranges() {
+        let mut model = Model::from(scrambled_grid(8));
+        let half = model.mesh.indices.len() as u32 / 2;
+        let mut second = model.submeshes[0].clone();
+        model.submeshes[0].indices.end = half;
+        second.indices.start = half;
+        model.submeshes.push(second);
+
+        let ranges = model
+            .submeshes
+            .iter()
+            .map(|s| sorted_triangles(model.submesh_indices(s), &model.mesh.vertices))
+            .collect::<Vec<_>>();
+        model.optimize(&OptimizeOptions::default());
+        for (submesh, before) in model.submeshes.iter().zip(ranges) {
+            let after = sorted_triangles(model.submesh_indices(submesh), &model.mesh.vertices);
+            assert_eq!(after, before);
+        }
+    }
+}
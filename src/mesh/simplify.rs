@@ -0,0 +1,555 @@
+use std::collections::HashMap;
+
+use glam::{DVec3, Vec3};
+
+use super::{Mesh, Vertex};
+use crate::{bounds::Aabb, camera::Camera};
+
+/// Symmetric 4x4 error quadric of Garland and Heckbert, with the total weight
+/// of its planes.
+#[derive(Clone, Copy, Debug, Default)]
+struct Quadric {
+    a: [f64; 10],
+    weight: f64,
+}
+
+impl Quadric {
+    /// Squared distance to the plane `n . p + d = 0`, scaled by `weight`.
+    fn plane(n: DVec3, d: f64, weight: f64) -> Self {
+        Quadric {
+            weight,
+            a: [
+                n.x * n.x,
+                n.x * n.y,
+                n.x * n.z,
+                n.x * d,
+                n.y * n.y,
+                n.y * n.z,
+                n.y * d,
+                n.z * n.z,
+                n.z * d,
+                d * d,
+            ]
+            .map(|v| v * weight),
+        }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for (a, b) in self.a.iter_mut().zip(other.a) {
+            *a += b;
+        }
+        self.weight += other.weight;
+    }
+
+    /// Weighted mean of the squared distances from `p` to the planes, so the
+    /// error does not grow with the number or the area of the triangles.
+    fn error(&self, p: DVec3) -> f64 {
+        if self.weight <= 0. {
+            return 0.;
+        }
+        let a = &self.a;
+        let (x, y, z) = (p.x, p.y, p.z);
+        let e = a[0] * x * x
+            + 2. * a[1] * x * y
+            + 2. * a[2] * x * z
+            + 2. * a[3] * x
+            + a[4] * y * y
+            + 2. * a[5] * y * z
+            + 2. * a[6] * y
+            + a[7] * z * z
+            + 2. * a[8] * z
+            + a[9];
+        (e / self.weight).max(0.)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VertexKind {
+    /// Interior vertex, free to collapse onto any neighbour.
+    Manifold,
+    /// On an open edge, may only slide along the border.
+    Border,
+    /// Shares its position with another vertex (UV or normal seam), never moves.
+    Locked,
+}
+
+/// Reduce `indices` to at most `target_index_count` by collapsing edges in
+/// order of quadric error, without moving vertices: the result indexes the same
+/// vertex buffer. Stops early once the next collapse would exceed
+/// `target_error`, relative to the mesh extent. Returns the new indices and the
+/// relative error reached.
+pub fn simplify(
+    indices: &[u32],
+    vertices: &[Vertex],
+    target_index_count: usize,
+    target_error: f32,
+) -> (Vec<u32>, f32) {
+    let mut indices = indices.to_vec();
+    let aabb = Aabb::from_points(
+        indices
+            .iter()
+            .map(|&i| Vec3::from(vertices[i as usize].position)),
+    );
+    let scale = aabb.size().max_element().max(f32::MIN_POSITIVE) as f64;
+    let positions = vertices
+        .iter()
+        .map(|v| (Vec3::from(v.position) - aabb.min).as_dvec3() / scale)
+        .collect::<Vec<_>>();
+
+    let kinds = classify(&indices, vertices);
+    let mut quadrics = vec![Quadric::default(); vertices.len()];
+    let mut edge_count: HashMap<(u32, u32), u32> = HashMap::new();
+    for tri in indices.chunks_exact(3) {
+        let p = [0, 1, 2].map(|k| positions[tri[k] as usize]);
+        let normal = (p[1] - p[0]).cross(p[2] - p[0]);
+        let area = normal.length();
+        if area > 0. {
+            let n = normal / area;
+            let q = Quadric::plane(n, -n.dot(p[0]), area);
+            tri.iter().for_each(|&i| quadrics[i as usize].add(&q));
+        }
+        for k in 0..3 {
+            let (a, b) = (tri[k], tri[(k + 1) % 3]);
+            *edge_count.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+    // planes through border edges keep the outline in place
+    for tri in indices.chunks_exact(3) {
+        let p = [0, 1, 2].map(|k| positions[tri[k] as usize]);
+        let normal = (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero();
+        for k in 0..3 {
+            let (a, b) = (tri[k], tri[(k + 1) % 3]);
+            if edge_count[&(a.min(b), a.max(b))] == 1 {
+                let edge = p[(k + 1) % 3] - p[k];
+                let n = edge.cross(normal).normalize_or_zero();
+                let q = Quadric::plane(n, -n.dot(p[k]), edge.length_squared() * 10.);
+                quadrics[a as usize].add(&q);
+                quadrics[b as usize].add(&q);
+            }
+        }
+    }
+
+    let max_error = (target_error as f64).powi(2);
+    let mut reached = 0_f64;
+    let mut remap = (0..vertices.len() as u32).collect::<Vec<_>>();
+    while indices.len() > target_index_count {
+        // best collapse for every vertex, u -> v
+        let mut candidates: HashMap<u32, (f64, u32)> = HashMap::new();
+        for tri in indices.chunks_exact(3) {
+            for k in 0..3 {
+                for (u, v) in [(tri[k], tri[(k + 1) % 3]), (tri[(k + 1) % 3], tri[k])] {
+                    if !can_collapse(&kinds, &edge_count, u, v) {
+                        continue;
+                    }
+                    let mut q = quadrics[u as usize];
+                    q.add(&quadrics[v as usize]);
+                    let cost = q.error(positions[v as usize]);
+                    let best = candidates.entry(u).or_insert((f64::INFINITY, v));
+                    if cost < best.0 {
+                        *best = (cost, v);
+                    }
+                }
+            }
+        }
+        let mut candidates = candidates
+            .into_iter()
+            .map(|(u, (cost, v))| (cost, u, v))
+            .filter(|&(cost, _, _)| cost <= max_error)
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        let mut adjacency: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (t, tri) in indices.chunks_exact(3).enumerate() {
+            tri.iter()
+                .for_each(|&i| adjacency.entry(i).or_default().push(t));
+        }
+
+        // every collapse removes about two triangles
+        let budget = (indices.len() - target_index_count).div_ceil(6).max(1);
+        let mut touched = vec![false; vertices.len()];
+        let mut collapsed = 0;
+        for (cost, u, v) in candidates {
+            if collapsed >= budget {
+                break;
+            }
+            let neighbours = &adjacency[&u];
+            let dirty = |i: u32| touched[i as usize];
+            if dirty(u)
+                || dirty(v)
+                || neighbours
+                    .iter()
+                    .any(|&t| indices[3 * t..3 * t + 3].iter().any(|&i| dirty(i)))
+            {
+                continue;
+            }
+            if flips(&indices, &positions, neighbours, u, v) {
+                continue;
+            }
+            for &t in neighbours {
+                indices[3 * t..3 * t + 3]
+                    .iter()
+                    .for_each(|&i| touched[i as usize] = true);
+            }
+            remap[u as usize] = v;
+            let q = quadrics[u as usize];
+            quadrics[v as usize].add(&q);
+            reached = reached.max(cost);
+            collapsed += 1;
+        }
+        if collapsed == 0 {
+            break;
+        }
+
+        for i in &mut indices {
+            *i = remap[*i as usize];
+        }
+        let mut kept = Vec::with_capacity(indices.len());
+        for tri in indices.chunks_exact(3) {
+            if tri[0] != tri[1] && tri[1] != tri[2] && tri[0] != tri[2] {
+                kept.extend_from_slice(tri);
+            }
+        }
+        indices = kept;
+
+        edge_count.clear();
+        for tri in indices.chunks_exact(3) {
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                *edge_count.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+    }
+    (indices, reached.sqrt() as f32)
+}
+
+fn classify(indices: &[u32], vertices: &[Vertex]) -> Vec<VertexKind> {
+    let mut kinds = vec![VertexKind::Manifold; vertices.len()];
+    let mut edges: HashMap<(u32, u32), u32> = HashMap::new();
+    for tri in indices.chunks_exact(3) {
+        for k in 0..3 {
+            let (a, b) = (tri[k], tri[(k + 1) % 3]);
+            *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+    for (&(a, b), &count) in &edges {
+        let kind = if count == 1 {
+            VertexKind::Border
+        } else if count > 2 {
+            VertexKind::Locked
+        } else {
+            continue;
+        };
+        for i in [a, b] {
+            if kinds[i as usize] != VertexKind::Locked {
+                kinds[i as usize] = kind;
+            }
+        }
+    }
+    let mut positions: HashMap<[u32; 3], u32> = HashMap::new();
+    for &i in indices {
+        let key = vertices[i as usize].position.map(f32::to_bits);
+        let first = *positions.entry(key).or_insert(i);
+        if first != i {
+            kinds[i as usize] = VertexKind::Locked;
+            kinds[first as usize] = VertexKind::Locked;
+        }
+    }
+    kinds
+}
+
+fn can_collapse(kinds: &[VertexKind], edges: &HashMap<(u32, u32), u32>, u: u32, v: u32) -> bool {
+    match (kinds[u as usize], kinds[v as usize]) {
+        (VertexKind::Manifold, _) => true,
+        (VertexKind::Border, VertexKind::Border) => edges[&(u.min(v), u.max(v))] == 1,
+        _ => false,
+    }
+}
+
+/// Whether moving `u` onto `v` turns any remaining triangle around `u` over.
+fn flips(indices: &[u32], positions: &[DVec3], triangles: &[usize], u: u32, v: u32) -> bool {
+    triangles.iter().any(|&t| {
+        let tri = &indices[3 * t..3 * t + 3];
+        if tri.contains(&v) {
+            return false;
+        }
+        let p = [0, 1, 2].map(|k| positions[tri[k] as usize]);
+        let moved = [0, 1, 2].map(|k| {
+            if tri[k] == u {
+                positions[v as usize]
+            } else {
+                p[k]
+            }
+        });
+        let before = (p[1] - p[0]).cross(p[2] - p[0]);
+        let after = (moved[1] - moved[0]).cross(moved[2] - moved[0]);
+        before.dot(after) <= 1e-2 * before.length() * after.length()
+    })
+}
+
+/// Settings for [`Mesh::lod_chain`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodOptions {
+    /// Number of levels after the original one.
+    pub levels: usize,
+    /// Triangle count of each level relative to the previous one.
+    pub reduction: f32,
+    /// Largest error allowed for any level, relative to the mesh extent.
+    pub max_error: f32,
+}
+
+impl Default for LodOptions {
+    fn default() -> Self {
+        LodOptions {
+            levels: 4,
+            reduction: 0.5,
+            max_error: 0.05,
+        }
+    }
+}
+
+/// One level of a [`LodChain`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lod {
+    pub indices: Vec<u32>,
+    /// Geometric error in world units: the root mean square distance from the
+    /// collapsed vertices to the planes of the triangles they replaced. The
+    /// largest deviation from the original surface can be a few times more.
+    pub error: f32,
+}
+
+/// Index buffers of decreasing detail sharing the vertices of one mesh. Level 0
+/// is the original mesh.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LodChain {
+    pub levels: Vec<Lod>,
+}
+
+impl Mesh {
+    /// [`simplify`] into a chain of levels. Building stops early when a level
+    /// cannot get under its triangle target within `max_error`.
+    pub fn lod_chain(&self, options: &LodOptions) -> LodChain {
+        let scale = self.aabb().size().max_element();
+        let mut levels = vec![Lod {
+            indices: self.indices.clone(),
+            error: 0.,
+        }];
+        for _ in 0..options.levels {
+            let previous = levels.last().unwrap();
+            let target = ((previous.indices.len() / 3) as f32 * options.reduction) as usize * 3;
+            let (indices, error) =
+                simplify(&previous.indices, &self.vertices, target, options.max_error);
+            if indices.len() >= previous.indices.len() {
+                break;
+            }
+            let error = (error * scale).max(previous.error);
+            levels.push(Lod { indices, error });
+            if levels.last().unwrap().indices.len() > target {
+                break;
+            }
+        }
+        LodChain { levels }
+    }
+}
+
+impl LodChain {
+    /// The coarsest level whose error, projected on screen for an object at
+    /// `center`, stays under `max_pixel_error` in a viewport `viewport_height`
+    /// pixels tall.
+    pub fn select(
+        &self,
+        camera: &Camera,
+        center: Vec3,
+        viewport_height: f32,
+        max_pixel_error: f32,
+    ) -> usize {
+        let distance = camera.position().distance(center).max(f32::EPSILON);
+        let pixels_per_unit = viewport_height / camera.view_height(distance);
+        self.levels
+            .iter()
+            .rposition(|lod| lod.error * pixels_per_unit <= max_pixel_error)
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::VertexAttributes;
+
+    /// Closed UV sphere without duplicated seam vertices.
+    fn sphere(rings: u32, segments: u32) -> Mesh {
+        let mut vertices = vec![Vertex::new([0., 1., 0.], [0., 1., 0.])];
+        for r in 1..rings {
+            let theta = std::f32::consts::PI * r as f32 / rings as f32;
+            for s in 0..segments {
+                let phi = std::f32::consts::TAU * s as f32 / segments as f32;
+                let p = [
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                ];
+                vertices.push(Vertex::new(p, p));
+            }
+        }
+        vertices.push(Vertex::new([0., -1., 0.], [0., -1., 0.]));
+        let ring = |r: u32, s: u32| 1 + (r - 1) * segments + s % segments;
+        let mut indices = Vec::new();
+        for s in 0..segments {
+            indices.extend([0, ring(1, s + 1), ring(1, s)]);
+            let last = vertices.len() as u32 - 1;
+            indices.extend([last, ring(rings - 1, s), ring(rings - 1, s + 1)]);
+        }
+        for r in 1..rings - 1 {
+            for s in 0..segments {
+                indices.extend([ring(r, s), ring(r, s + 1), ring(r + 1, s + 1)]);
+                indices.extend([ring(r, s), ring(r + 1, s + 1), ring(r + 1, s)]);
+            }
+        }
+        Mesh {
+            vertices,
+            indices,
+            attributes: VertexAttributes {
+                normals: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn flat_grid_collapses_without_error() {
+        let n = 10;
+        let vertices = (0..=n)
+            .flat_map(|y| (0..=n).map(move |x| Vertex::new([x as f32, y as f32, 0.], [0., 0., 1.])))
+            .collect::<Vec<_>>();
+        let mut indices = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                indices.extend([i, i + 1, i + n + 2, i, i + n + 2, i + n + 1]);
+            }
+        }
+        let (simplified, error) = simplify(&indices, &vertices, 0, 1e-3);
+        assert!(
+            simplified.len() < indices.len() / 10,
+            "{}",
+            simplified.len()
+        );
+        assert!(error < 1e-3);
+        // the corners survive, so the outline does not shrink
+        let aabb = Aabb::from_points(
+            simplified
+                .iter()
+                .map(|&i| Vec3::from(vertices[i as usize].position)),
+        );
+        assert_eq!(aabb.min, Vec3::ZERO);
+        assert_eq!(aabb.max, Vec3::new(n as f32, n as f32, 0.));
+    }
+
+    #[test]
+    fn sphere_lod_chain_halves_triangles_within_error() {
+        let mesh = sphere(24, 48);
+        let chain = mesh.lod_chain(&LodOptions::default());
+        assert_eq!(chain.levels.len(), 5);
+        for pair in chain.levels.windows(2) {
+            let (fine, coarse) = (pair[0].indices.len(), pair[1].indices.len());
+            assert!(coarse <= fine / 2 + 3, "{} -> {}", fine, coarse);
+            assert!(coarse > 0);
+            assert!(pair[1].error >= pair[0].error);
+        }
+        // the unit sphere spans 2 units, errors are in world units
+        assert!(chain.levels.last().unwrap().error <= 0.05 * 2.);
+
+        // every remaining vertex is still on the sphere
+        for &i in &chain.levels[4].indices {
+            let p = Vec3::from(mesh.vertices[i as usize].position);
+            assert!((p.length() - 1.).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn error_bound_stops_simplification() {
+        let mesh = sphere(12, 24);
+        let (indices, error) = simplify(&mesh.indices, &mesh.vertices, 0, 1e-4);
+        assert_eq!(indices.len(), mesh.indices.len());
+        assert_eq!(error, 0.);
+    }
+
+    #[test]
+    fn selection_gets_coarser_with_distance() {
+        let mesh = sphere(24, 48);
+        let chain = mesh.lod_chain(&LodOptions::default());
+        let near = Camera::new().with_position(Vec3::new(0., 0., -3.));
+        let far = Camera::new().with_position(Vec3::new(0., 0., -300.));
+        assert_eq!(chain.select(&near, Vec3::ZERO, 1200., 1.), 0);
+        assert_eq!(
+            chain.select(&far, Vec3::ZERO, 1200., 1.),
+            chain.levels.len() - 1
+        );
+    }
+
+    /// Distance from `p` to the triangle `t`.
+    fn triangle_distance(p: Vec3, [a, b, c]: [Vec3; 3]) -> f32 {
+        let normal = (b - a).cross(c - a).normalize();
+        let inside = [(a, b), (b, c), (c, a)]
+            .iter()
+            .all(|&(u, v)| (v - u).cross(p - u).dot(normal) >= 0.);
+        if inside {
+            return (p - a).dot(normal).abs();
+        }
+        [(a, b), (b, c), (c, a)]
+            .iter()
+            .map(|&(u, v)| {
+                let t = ((p - u).dot(v - u) / (v - u).length_squared()).clamp(0., 1.);
+                p.distance(u + (v - u) * t)
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn error_matches_the_measured_deviation() {
+        // a 4x4 plane with a smooth bump 0.2 high in the middle
+        let n = 32;
+        let vertices = (0..=n)
+            .flat_map(|y| {
+                (0..=n).map(move |x| {
+                    let (u, v) = (x as f32 / n as f32 * 4. - 2., y as f32 / n as f32 * 4. - 2.);
+                    let z = 0.2 * (-(u * u + v * v) * 2.).exp();
+                    Vertex::new([u, v, z], [0., 0., 1.])
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut indices = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                indices.extend([i, i + 1, i + n + 2, i, i + n + 2, i + n + 1]);
+            }
+        }
+        for target_error in [0.001, 0.003, 0.01] {
+            let (simplified, error) = simplify(&indices, &vertices, 0, target_error);
+            let error = error * 4.;
+            let triangles = simplified
+                .chunks_exact(3)
+                .map(|t| [0, 1, 2].map(|k| Vec3::from(vertices[t[k] as usize].position)))
+                .collect::<Vec<_>>();
+            let measured = vertices
+                .iter()
+                .map(|v| {
+                    let p = Vec3::from(v.position);
+                    triangles
+                        .iter()
+                        .map(|&t| triangle_distance(p, t))
+                        .fold(f32::INFINITY, f32::min)
+                })
+                .fold(0., f32::max);
+            // quadrics average the planes around a vertex, so the error stays
+            // under the largest deviation but of the same order
+            assert!(
+                measured / 3. <= error && error <= measured,
+                "error {} for a deviation of {}",
+                error,
+                measured
+            );
+        }
+    }
+}
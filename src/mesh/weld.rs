@@ -0,0 +1,268 @@
+use std::{collections::HashMap, ops::Range};
+
+use glam::{IVec3, Vec2, Vec3, Vec4};
+
+use super::{Mesh, Model, Vertex, VertexAttributes};
+
+/// Tolerances used by [`Mesh::weld`]. Two vertices merge only if every
+/// attribute the mesh carries is within its tolerance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeldOptions {
+    pub position_epsilon: f32,
+    /// Also the tolerance of the tangents, which must have the same
+    /// handedness.
+    pub normal_epsilon: f32,
+    pub tex_coord_epsilon: f32,
+    pub color_epsilon: f32,
+}
+
+impl Default for WeldOptions {
+    fn default() -> Self {
+        WeldOptions {
+            position_epsilon: 1e-6,
+            normal_epsilon: 1e-3,
+            tex_coord_epsilon: 1e-5,
+            color_epsilon: 1e-3,
+        }
+    }
+}
+
+/// What [`Mesh::weld`] removed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WeldReport {
+    /// Vertices folded into an equivalent one.
+    pub merged_vertices: usize,
+    /// Vertices no triangle referenced anymore.
+    pub unused_vertices: usize,
+    /// Triangles with repeated vertices or no area.
+    pub degenerate_triangles: usize,
+}
+
+impl Mesh {
+    /// Merge vertices that are equal within `options`, then drop degenerate
+    /// triangles and vertices left unreferenced.
+    pub fn weld(&mut self, options: &WeldOptions) -> WeldReport {
+        self.weld_ranges(options, &mut [])
+    }
+
+    fn weld_ranges(&mut self, options: &WeldOptions, ranges: &mut [Range<u32>]) -> WeldReport {
+        let mut report = WeldReport::default();
+
+        let remap = weld_map(&self.vertices, self.attributes, options);
+        report.merged_vertices = remap
+            .iter()
+            .enumerate()
+            .filter(|&(i, &r)| i as u32 != r)
+            .count();
+        self.indices
+            .iter_mut()
+            .for_each(|i| *i = remap[*i as usize]);
+
+        // keep triangles in place and shift the ranges past removed ones
+        let mut removed_before = Vec::with_capacity(self.indices.len() / 3 + 1);
+        let mut kept = 0;
+        for t in 0..self.indices.len() / 3 {
+            removed_before.push((t - kept) as u32);
+            let tri = [0, 1, 2].map(|k| self.indices[3 * t + k]);
+            if !is_degenerate(&self.vertices, tri) {
+                self.indices.copy_within(3 * t..3 * t + 3, 3 * kept);
+                kept += 1;
+            }
+        }
+        removed_before.push((self.indices.len() / 3 - kept) as u32);
+        report.degenerate_triangles = self.indices.len() / 3 - kept;
+        self.indices.truncate(3 * kept);
+        for range in ranges {
+            let start = removed_before[range.start as usize / 3];
+            let end = removed_before[range.end as usize / 3];
+            *range = range.start - 3 * start..range.end - 3 * end;
+        }
+
+        let mut compact = vec![u32::MAX; self.vertices.len()];
+        let mut vertices = Vec::with_capacity(self.vertices.len() - report.merged_vertices);
+        for i in &mut self.indices {
+            if compact[*i as usize] == u32::MAX {
+                compact[*i as usize] = vertices.len() as u32;
+                vertices.push(self.vertices[*i as usize]);
+            }
+            *i = compact[*i as usize];
+        }
+        report.unused_vertices = self.vertices.len() - report.merged_vertices - vertices.len();
+        self.vertices = vertices;
+        report
+    }
+}
+
+impl Model {
+    /// [`Mesh::weld`] keeping the submesh ranges in sync.
+    pub fn weld(&mut self, options: &WeldOptions) -> WeldReport {
+        let mut ranges = self
+            .submeshes
+            .iter()
+            .map(|s| s.indices.clone())
+            .collect::<Vec<_>>();
+        let report = self.mesh.weld_ranges(options, &mut ranges);
+        for (submesh, range) in self.submeshes.iter_mut().zip(ranges) {
+            submesh.indices = range;
+        }
+        report
+    }
+}
+
+/// For every vertex, the index of the first equivalent vertex.
+fn weld_map(vertices: &[Vertex], attributes: VertexAttributes, options: &WeldOptions) -> Vec<u32> {
+    let cell_size = options.position_epsilon.max(f32::MIN_POSITIVE) * 2.;
+    let cell = |p: Vec3| (p / cell_size).floor().as_ivec3();
+    let same = |a: &Vertex, b: &Vertex| {
+        Vec3::from(a.position).abs_diff_eq(b.position.into(), options.position_epsilon)
+            && (!attributes.normals
+                || Vec3::from(a.normal).abs_diff_eq(b.normal.into(), options.normal_epsilon))
+            && (!attributes.tangents
+                || (a.tangent[3] == b.tangent[3]
+                    && Vec3::from_slice(&a.tangent[..3])
+                        .abs_diff_eq(Vec3::from_slice(&b.tangent[..3]), options.normal_epsilon)))
+            && (!attributes.tex_coords
+                || Vec2::from(a.tex_coords)
+                    .abs_diff_eq(b.tex_coords.into(), options.tex_coord_epsilon))
+            && (!attributes.colors
+                || Vec4::from(a.color).abs_diff_eq(b.color.into(), options.color_epsilon))
+    };
+
+    let mut grid: HashMap<_, Vec<u32>> = HashMap::new();
+    let mut remap = Vec::with_capacity(vertices.len());
+    for (i, v) in vertices.iter().enumerate() {
+        let c = cell(v.position.into());
+        let mut found = None;
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbour = c + IVec3::new(dx, dy, dz);
+                    let Some(candidates) = grid.get(&neighbour) else {
+                        continue;
+                    };
+                    if let Some(&j) = candidates.iter().find(|&&j| same(&vertices[j as usize], v)) {
+                        found = Some(j);
+                        break 'search;
+                    }
+                }
+            }
+        }
+        remap.push(found.unwrap_or_else(|| {
+            grid.entry(c).or_default().push(i as u32);
+            i as u32
+        }));
+    }
+    remap
+}
+
+fn is_degenerate(vertices: &[Vertex], [a, b, c]: [u32; 3]) -> bool {
+    if a == b || b == c || a == c {
+        return true;
+    }
+    let [pa, pb, pc] = [a, b, c].map(|i| Vec3::from(vertices[i as usize].position));
+    (pb - pa).cross(pc - pa) == Vec3::ZERO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::SubMesh;
+
+    #[test]
+    fn duplicates_merge_and_degenerates_go() {
+        let p = [[0., 0., 0.], [1., 0., 0.], [0., 1., 0.], [1., 1., 0.]];
+        let mut vertices = p
+            .iter()
+            .map(|&p| Vertex::new(p, [0., 0., 1.]))
+            .collect::<Vec<_>>();
+        // copies of vertices 1 and 2 within tolerance
+        vertices.push(Vertex::new([1., 0., 1e-7], [0., 0., 1.]));
+        vertices.push(Vertex::new([0., 1., 0.], [0., 0., 1.]));
+        let mut model = Model {
+            mesh: Mesh {
+                vertices,
+                // the middle triangle collapses once 5 merges into 2
+                indices: vec![0, 1, 2, 2, 5, 3, 4, 3, 5],
+                attributes: VertexAttributes {
+                    normals: true,
+                    ..Default::default()
+                },
+            },
+            submeshes: vec![
+                SubMesh {
+                    name: "a".to_owned(),
+                    indices: 0..6,
+                    material: None,
+                },
+                SubMesh {
+                    name: "b".to_owned(),
+                    indices: 6..9,
+                    material: None,
+                },
+            ],
+            materials: Vec::new(),
+        };
+
+        let report = model.weld(&WeldOptions::default());
+        assert_eq!(
+            report,
+            WeldReport {
+                merged_vertices: 2,
+                unused_vertices: 0,
+                degenerate_triangles: 1,
+            }
+        );
+        assert_eq!(model.mesh.vertices.len(), 4);
+        assert_eq!(model.mesh.indices, [0, 1, 2, 1, 3, 2]);
+        assert_eq!(model.submeshes[0].indices, 0..3);
+        assert_eq!(model.submeshes[1].indices, 3..6);
+    }
+
+    #[test]
+    fn differing_normals_are_kept_apart() {
+        let mut mesh = Mesh {
+            vertices: vec![
+                Vertex::new([0., 0., 0.], [0., 0., 1.]),
+                Vertex::new([1., 0., 0.], [0., 0., 1.]),
+                Vertex::new([0., 1., 0.], [0., 0., 1.]),
+                Vertex::new([0., 0., 0.], [1., 0., 0.]),
+                Vertex::new([0., 1., 0.], [1., 0., 0.]),
+                Vertex::new([0., 0., 1.], [1., 0., 0.]),
+            ],
+            indices: vec![0, 1, 2, 3, 4, 5],
+            attributes: VertexAttributes {
+                normals: true,
+                ..Default::default()
+            },
+        };
+        assert_eq!(mesh.weld(&WeldOptions::default()), WeldReport::default());
+
+        mesh.attributes.normals = false;
+        assert_eq!(mesh.weld(&WeldOptions::default()).merged_vertices, 2);
+        assert_eq!(mesh.vertices.len(), 4);
+    }
+
+    #[test]
+    fn differing_tangents_are_kept_apart() {
+        let tangent = |tangent: [f32; 4]| Vertex {
+            tangent,
+            ..Vertex::new([0., 0., 0.], [0., 0., 1.])
+        };
+        let vertices = [
+            tangent([1., 0., 0., 1.]),
+            tangent([0., 1., 0., 1.]),
+            // mirrored UVs
+            tangent([1., 0., 0., -1.]),
+            tangent([1., 0., 0., 1.]),
+        ];
+        let mut attributes = VertexAttributes {
+            normals: true,
+            tangents: true,
+            ..Default::default()
+        };
+        let options = WeldOptions::default();
+        assert_eq!(weld_map(&vertices, attributes, &options), [0, 1, 2, 0]);
+        attributes.tangents = false;
+        assert_eq!(weld_map(&vertices, attributes, &options), [0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,541 @@
+//! Binary snapshot of a loaded [`Model`], written next to its source file so
+//! later runs can skip parsing.
+//!
+//! All values are little endian. The file holds, in order:
+//!
+//! - a header: magic, format version, vertex stride, attribute flags, the
+//!   bounding box of the vertices, source and options hashes, and the hashed
+//!   files the model also depends on (MTL libraries)
+//! - the material table and the submesh table
+//! - the raw vertex and index data
+//!
+//! Paths are stored relative to the directory of the source file.
+
+use std::{
+    error::Error,
+    fmt, fs,
+    hash::Hasher,
+    io,
+    mem::size_of,
+    path::{Path, PathBuf},
+};
+
+use glam::Vec3;
+
+use crate::{
+    bounds::Aabb,
+    mesh::{Material, Mesh, Model, SubMesh, Vertex, VertexAttributes},
+};
+
+const MAGIC: &[u8; 8] = b"MESHCACH";
+/// Bump whenever the layout of the file or of [`Vertex`] changes.
+pub const VERSION: u32 = 3;
+const FLOATS_PER_VERTEX: usize = size_of::<Vertex>() / size_of::<f32>();
+const NONE: u32 = u32::MAX;
+/// Hash recorded for a dependency that does not exist, so the cache goes
+/// stale once it appears.
+const MISSING: u64 = 0;
+
+/// 64-bit FNV-1a, stable across platforms and runs unlike `DefaultHasher`.
+#[derive(Clone, Copy, Debug)]
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// What a cache must have been built from to be fresh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheKey {
+    /// Hash of the source file contents.
+    pub source: u64,
+    /// Hash of the load options that shaped the model.
+    pub options: u64,
+}
+
+#[derive(Debug)]
+pub enum MeshCacheError {
+    /// The cache file could not be read or written.
+    Io { path: PathBuf, source: io::Error },
+    /// The file does not start with the mesh cache magic.
+    NotACache,
+    /// The file was written by another version of the format.
+    Version { found: u32 },
+    /// The file was written for vertices of another size in bytes.
+    VertexStride { found: u32 },
+    /// The source, the options or one of the dependencies changed.
+    Stale,
+    /// The file ends early or holds out of range values.
+    Corrupt { message: String },
+}
+
+impl fmt::Display for MeshCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshCacheError::Io { path, source } => {
+                write!(f, "failed to access {}: {}", path.display(), source)
+            }
+            MeshCacheError::NotACache => write!(f, "not a mesh cache file"),
+            MeshCacheError::Version { found } => write!(
+                f,
+                "mesh cache version {} does not match the current version {}",
+                found, VERSION
+            ),
+            MeshCacheError::VertexStride { found } => write!(
+                f,
+                "mesh cache vertex stride {} does not match the current stride {}",
+                found,
+                size_of::<Vertex>()
+            ),
+            MeshCacheError::Stale => write!(f, "mesh cache is out of date"),
+            MeshCacheError::Corrupt { message } => write!(f, "corrupt mesh cache: {}", message),
+        }
+    }
+}
+
+impl Error for MeshCacheError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MeshCacheError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Where the cache of `source` lives: the same path with `.meshcache`
+/// appended, e.g. `bugatti.obj.meshcache`.
+pub fn cache_path(source: &Path) -> PathBuf {
+    let mut name = source.file_name().unwrap_or_default().to_owned();
+    name.push(".meshcache");
+    source.with_file_name(name)
+}
+
+/// Write `model` to the cache of `source`. `dependencies` are other files the
+/// model was built from, their contents are hashed so editing, creating or
+/// deleting them invalidates the cache.
+pub fn write_cache(
+    source: &Path,
+    key: &CacheKey,
+    dependencies: &[PathBuf],
+    model: &Model,
+) -> Result<(), MeshCacheError> {
+    let dir = source.parent().unwrap_or(Path::new(""));
+    let mut w = Writer::default();
+    w.bytes(MAGIC);
+    w.u32(VERSION);
+    w.u32(size_of::<Vertex>() as u32);
+    w.u32(attribute_flags(model.mesh.attributes));
+    let aabb = model.mesh.aabb();
+    w.floats(&aabb.min.to_array());
+    w.floats(&aabb.max.to_array());
+    w.u64(key.source);
+    w.u64(key.options);
+    w.u32(dependencies.len() as u32);
+    for path in dependencies {
+        let hash = dependency_hash(path).map_err(|source| MeshCacheError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        w.path(dir, path);
+        w.u64(hash);
+    }
+
+    w.u32(model.materials.len() as u32);
+    for m in &model.materials {
+        w.string(&m.name);
+        w.floats(&m.ambient);
+        w.floats(&m.diffuse);
+        w.floats(&m.specular);
+        w.floats(&[m.shininess, m.dissolve, m.metallic, m.roughness]);
+        for texture in [
+            &m.diffuse_texture,
+            &m.normal_texture,
+            &m.specular_texture,
+            &m.metallic_roughness_texture,
+        ] {
+            match texture {
+                Some(path) => w.path(dir, path),
+                None => w.u32(NONE),
+            }
+        }
+    }
+    w.u32(model.submeshes.len() as u32);
+    for s in &model.submeshes {
+        w.string(&s.name);
+        w.u32(s.indices.start);
+        w.u32(s.indices.end);
+        w.u32(s.material.map_or(NONE, |m| m as u32));
+    }
+
+    w.u32(model.mesh.vertices.len() as u32);
+    for v in &model.mesh.vertices {
+        w.floats(&v.position);
+        w.floats(&v.normal);
+        w.floats(&v.tex_coords);
+        w.floats(&v.tangent);
+        w.floats(&v.bitangent);
+        w.floats(&v.color);
+    }
+    w.u32(model.mesh.indices.len() as u32);
+    for &i in &model.mesh.indices {
+        w.u32(i);
+    }
+
+    // write then rename so an interrupted run never leaves half a cache
+    let path = cache_path(source);
+    let mut tmp = path.clone().into_os_string();
+    tmp.push(".tmp");
+    let io_error = |source| MeshCacheError::Io {
+        path: path.clone(),
+        source,
+    };
+    fs::write(&tmp, w.0).map_err(io_error)?;
+    fs::rename(&tmp, &path).map_err(io_error)
+}
+
+/// Read the cache of `source` and the bounding box of its vertices, failing
+/// with [`MeshCacheError::Stale`] unless it was written for `key` and its
+/// dependencies are unchanged.
+pub fn read_cache(source: &Path, key: &CacheKey) -> Result<(Model, Aabb), MeshCacheError> {
+    let dir = source.parent().unwrap_or(Path::new(""));
+    let path = cache_path(source);
+    let bytes = fs::read(&path).map_err(|source| MeshCacheError::Io {
+        path: path.clone(),
+        source,
+    })?;
+    let mut r = Reader { bytes: &bytes };
+
+    if r.take(MAGIC.len()).ok() != Some(MAGIC) {
+        return Err(MeshCacheError::NotACache);
+    }
+    let version = r.u32()?;
+    if version != VERSION {
+        return Err(MeshCacheError::Version { found: version });
+    }
+    let stride = r.u32()?;
+    if stride as usize != size_of::<Vertex>() {
+        return Err(MeshCacheError::VertexStride { found: stride });
+    }
+    let attributes = attributes_from_flags(r.u32()?);
+    let aabb = Aabb {
+        min: Vec3::from(r.array()?),
+        max: Vec3::from(r.array()?),
+    };
+    if (CacheKey {
+        source: r.u64()?,
+        options: r.u64()?,
+    }) != *key
+    {
+        return Err(MeshCacheError::Stale);
+    }
+    for _ in 0..r.u32()? {
+        let dependency = r.path(dir)?;
+        let hash = r.u64()?;
+        if dependency_hash(&dependency).ok() != Some(hash) {
+            return Err(MeshCacheError::Stale);
+        }
+    }
+
+    let mut model = Model::default();
+    for _ in 0..r.u32()? {
+        let name = r.string()?;
+        let [ambient, diffuse, specular] = [r.array()?, r.array()?, r.array()?];
+        let [shininess, dissolve, metallic, roughness] = r.array()?;
+        let mut texture = || -> Result<Option<PathBuf>, MeshCacheError> { r.optional_path(dir) };
+        model.materials.push(Material {
+            name,
+            ambient,
+            diffuse,
+            specular,
+            shininess,
+            dissolve,
+            metallic,
+            roughness,
+            diffuse_texture: texture()?,
+            normal_texture: texture()?,
+            specular_texture: texture()?,
+            metallic_roughness_texture: texture()?,
+        });
+    }
+    for _ in 0..r.u32()? {
+        let name = r.string()?;
+        let indices = r.u32()?..r.u32()?;
+        let material = match r.u32()? {
+            NONE => None,
+            m if (m as usize) < model.materials.len() => Some(m as usize),
+            m => return Err(corrupt(format!("submesh {:?} uses material {}", name, m))),
+        };
+        model.submeshes.push(SubMesh {
+            name,
+            indices,
+            material,
+        });
+    }
+
+    let vertex_count = r.u32()? as usize;
+    let data = r.take(vertex_count * FLOATS_PER_VERTEX * 4)?;
+    let mut vertices = Vec::with_capacity(vertex_count);
+    for chunk in data.chunks_exact(FLOATS_PER_VERTEX * 4) {
+        let mut v = Reader { bytes: chunk };
+        vertices.push(Vertex {
+            position: v.array()?,
+            normal: v.array()?,
+            tex_coords: v.array()?,
+            tangent: v.array()?,
+            bitangent: v.array()?,
+            color: v.array()?,
+        });
+    }
+    let index_count = r.u32()? as usize;
+    let indices = r
+        .take(index_count * 4)?
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect::<Vec<_>>();
+    if indices.iter().any(|&i| i as usize >= vertex_count) {
+        return Err(corrupt("index out of range".to_owned()));
+    }
+    if model
+        .submeshes
+        .iter()
+        .any(|s| s.indices.start > s.indices.end || s.indices.end as usize > index_count)
+    {
+        return Err(corrupt("submesh out of range".to_owned()));
+    }
+    if !r.bytes.is_empty() {
+        return Err(corrupt(format!("{} trailing bytes", r.bytes.len())));
+    }
+
+    model.mesh = Mesh {
+        vertices,
+        indices,
+        attributes,
+    };
+    Ok((model, aabb))
+}
+
+/// Hash of the contents of `path`, or [`MISSING`] if there is no such file.
+fn dependency_hash(path: &Path) -> io::Result<u64> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(fnv1a(&bytes)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(MISSING),
+        Err(e) => Err(e),
+    }
+}
+
+fn corrupt(message: String) -> MeshCacheError {
+    MeshCacheError::Corrupt { message }
+}
+
+fn attribute_flags(a: VertexAttributes) -> u32 {
+    a.normals as u32
+        | (a.tex_coords as u32) << 1
+        | (a.tangents as u32) << 2
+        | (a.colors as u32) << 3
+}
+
+fn attributes_from_flags(flags: u32) -> VertexAttributes {
+    VertexAttributes {
+        normals: flags & 1 != 0,
+        tex_coords: flags & 2 != 0,
+        tangents: flags & 4 != 0,
+        colors: flags & 8 != 0,
+    }
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn floats(&mut self, values: &[f32]) {
+        values.iter().for_each(|v| self.bytes(&v.to_le_bytes()));
+    }
+
+    fn string(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.bytes(s.as_bytes());
+    }
+
+    fn path(&mut self, dir: &Path, path: &Path) {
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        self.string(&relative.to_string_lossy());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], MeshCacheError> {
+        if n > self.bytes.len() {
+            return Err(corrupt("unexpected end of file".to_owned()));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, MeshCacheError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, MeshCacheError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[f32; N], MeshCacheError> {
+        let bytes = self.take(4 * N)?;
+        Ok(std::array::from_fn(|i| {
+            f32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap())
+        }))
+    }
+
+    fn string_of_len(&mut self, len: u32) -> Result<String, MeshCacheError> {
+        let bytes = self.take(len as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| corrupt("invalid UTF-8 string".to_owned()))
+    }
+
+    fn string(&mut self) -> Result<String, MeshCacheError> {
+        let len = self.u32()?;
+        self.string_of_len(len)
+    }
+
+    fn path(&mut self, dir: &Path) -> Result<PathBuf, MeshCacheError> {
+        Ok(dir.join(self.string()?))
+    }
+
+    fn optional_path(&mut self, dir: &Path) -> Result<Option<PathBuf>, MeshCacheError> {
+        match self.u32()? {
+            NONE => Ok(None),
+            len => Ok(Some(dir.join(self.string_of_len(len)?))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn fnv1a_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn model_round_trips() {
+        let dir = TempDir::new("mesh_cache_round_trip");
+        let source = dir.join("cube.obj");
+        let mtl = dir.join("cube.mtl");
+        fs::write(&mtl, "newmtl red\n").unwrap();
+
+        let mut model = Model::placeholder();
+        model.mesh.vertices[3].color = [0.5, 0.25, 1., 1.];
+        model.materials.push(Material {
+            name: "red".to_owned(),
+            diffuse_texture: Some(dir.join("textures/red.png")),
+            ..Default::default()
+        });
+        model.submeshes[0].material = Some(0);
+        let key = CacheKey {
+            source: 1,
+            options: 2,
+        };
+        write_cache(&source, &key, std::slice::from_ref(&mtl), &model).unwrap();
+        assert_eq!(cache_path(&source), dir.join("cube.obj.meshcache"));
+
+        let (cached, aabb) = read_cache(&source, &key).unwrap();
+        assert_eq!(aabb, model.mesh.aabb());
+        assert_eq!(aabb.max, Vec3::ONE);
+        assert_eq!(cached.mesh, model.mesh);
+        assert_eq!(cached.submeshes, model.submeshes);
+        assert_eq!(cached.materials, model.materials);
+
+        let other = CacheKey { source: 3, ..key };
+        assert!(matches!(
+            read_cache(&source, &other),
+            Err(MeshCacheError::Stale)
+        ));
+        fs::write(&mtl, "newmtl blue\n").unwrap();
+        assert!(matches!(
+            read_cache(&source, &key),
+            Err(MeshCacheError::Stale)
+        ));
+    }
+
+    #[test]
+    fn damaged_files_are_rejected() {
+        let dir = TempDir::new("mesh_cache_damaged");
+        let source = dir.join("cube.obj");
+        let key = CacheKey {
+            source: 0,
+            options: 0,
+        };
+        write_cache(&source, &key, &[], &Model::placeholder()).unwrap();
+        let path = cache_path(&source);
+        let bytes = fs::read(&path).unwrap();
+
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(
+            read_cache(&source, &key),
+            Err(MeshCacheError::Corrupt { .. })
+        ));
+
+        let mut other = bytes.clone();
+        other[8] += 1;
+        fs::write(&path, &other).unwrap();
+        assert!(matches!(
+            read_cache(&source, &key),
+            Err(MeshCacheError::Version { found }) if found == VERSION + 1
+        ));
+
+        let mut other = bytes.clone();
+        other[12] += 4;
+        fs::write(&path, &other).unwrap();
+        assert!(matches!(
+            read_cache(&source, &key),
+            Err(MeshCacheError::VertexStride { found }) if found as usize == size_of::<Vertex>() + 4
+        ));
+
+        fs::write(&path, b"v 0 0 0\n").unwrap();
+        assert!(matches!(
+            read_cache(&source, &key),
+            Err(MeshCacheError::NotACache)
+        ));
+    }
+}
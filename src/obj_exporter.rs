@@ -0,0 +1,269 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::mesh::{Material, Model};
+
+/// Write `model` to an OBJ file, and its materials to an MTL library with the
+/// same name next to it. Every submesh becomes an OBJ group using its
+/// material, or a default one added to the library when it has none. Vertex
+/// colours and tangents are not written.
+pub fn save_obj<P>(path: P, model: &Model) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let mtl_path = path.with_extension("mtl");
+    let mut materials = model.materials.clone();
+    if model.submeshes.iter().any(|s| model.material(s).is_none()) {
+        materials.push(Material {
+            name: default_material_name(&model.materials),
+            ..Default::default()
+        });
+    }
+    let mtl_name = if materials.is_empty() {
+        None
+    } else {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+        write_mtl(&mut mtl, &materials, dir)?;
+        mtl.flush()?;
+        mtl_path.file_name().and_then(|n| n.to_str())
+    };
+    let mut obj = BufWriter::new(File::create(path)?);
+    write_obj(&mut obj, model, mtl_name)?;
+    obj.flush()
+}
+
+/// Write `model` as OBJ text, referencing the MTL library `mtl_name` if any.
+/// Submeshes without a material use the one named by
+/// [`default_material_name`], since OBJ readers otherwise carry the previous
+/// material over.
+pub fn write_obj<W>(mut w: W, model: &Model, mtl_name: Option<&str>) -> io::Result<()>
+where
+    W: Write,
+{
+    let mesh = &model.mesh;
+    let attributes = mesh.attributes;
+    writeln!(
+        w,
+        "# {} vertices, {} triangles",
+        mesh.vertices.len(),
+        mesh.indices.len() / 3
+    )?;
+    if let Some(name) = mtl_name {
+        writeln!(w, "mtllib {}", name)?;
+    }
+
+    // `{}` prints the shortest representation that reads back to the same f32
+    for v in &mesh.vertices {
+        let [x, y, z] = v.position;
+        writeln!(w, "v {} {} {}", x, y, z)?;
+    }
+    if attributes.tex_coords {
+        for v in &mesh.vertices {
+            writeln!(w, "vt {} {}", v.tex_coords[0], v.tex_coords[1])?;
+        }
+    }
+    if attributes.normals {
+        for v in &mesh.vertices {
+            let [x, y, z] = v.normal;
+            writeln!(w, "vn {} {} {}", x, y, z)?;
+        }
+    }
+
+    // OBJ indices count from 1 and all three arrays share ours
+    let corner = |i: u32| match (attributes.tex_coords, attributes.normals) {
+        (false, false) => format!("{}", i + 1),
+        (true, false) => format!("{0}/{0}", i + 1),
+        (false, true) => format!("{0}//{0}", i + 1),
+        (true, true) => format!("{0}/{0}/{0}", i + 1),
+    };
+    let faces = |w: &mut W, indices: &[u32]| -> io::Result<()> {
+        for tri in indices.chunks_exact(3) {
+            writeln!(
+                w,
+                "f {} {} {}",
+                corner(tri[0]),
+                corner(tri[1]),
+                corner(tri[2])
+            )?;
+        }
+        Ok(())
+    };
+    if model.submeshes.is_empty() {
+        return faces(&mut w, &mesh.indices);
+    }
+    let default_material = default_material_name(&model.materials);
+    for (i, submesh) in model.submeshes.iter().enumerate() {
+        if submesh.name.is_empty() {
+            writeln!(w, "g submesh{}", i)?;
+        } else {
+            writeln!(w, "g {}", submesh.name)?;
+        }
+        let material = model.material(submesh).map(|m| m.name.as_str());
+        writeln!(w, "usemtl {}", material.unwrap_or(&default_material))?;
+        faces(&mut w, model.submesh_indices(submesh))?;
+    }
+    Ok(())
+}
+
+/// Name for the material of submeshes without one, `default` unless one of
+/// `materials` already has it.
+pub fn default_material_name(materials: &[Material]) -> String {
+    let taken = |name: &str| materials.iter().any(|m| m.name == name);
+    (0..)
+        .map(|i| match i {
+            0 => "default".to_owned(),
+            i => format!("default{}", i),
+        })
+        .find(|name| !taken(name))
+        .unwrap()
+}
+
+/// Write `materials` as an MTL library. Texture paths are made relative to
+/// `dir`, the directory the library is saved in, when they are inside it.
+pub fn write_mtl<W>(mut w: W, materials: &[Material], dir: &Path) -> io::Result<()>
+where
+    W: Write,
+{
+    for (i, m) in materials.iter().enumerate() {
+        if i > 0 {
+            writeln!(w)?;
+        }
+        writeln!(w, "newmtl {}", m.name)?;
+        for (key, [r, g, b]) in [("Ka", m.ambient), ("Kd", m.diffuse), ("Ks", m.specular)] {
+            writeln!(w, "{} {} {} {}", key, r, g, b)?;
+        }
+        writeln!(w, "Ns {}", m.shininess)?;
+        writeln!(w, "d {}", m.dissolve)?;
+        // PBR extension of the MTL format
+        writeln!(w, "Pm {}", m.metallic)?;
+        writeln!(w, "Pr {}", m.roughness)?;
+        for (key, texture) in [
+            ("map_Kd", &m.diffuse_texture),
+            ("map_Bump", &m.normal_texture),
+            ("map_Ks", &m.specular_texture),
+        ] {
+            if let Some(texture) = texture {
+                let texture = texture.strip_prefix(dir).unwrap_or(texture);
+                writeln!(w, "{} {}", key, texture.display())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mesh::{Mesh, SubMesh, Vertex},
+        obj_loader::{try_load_model_with, ObjLoadOptions},
+        test_util::TempDir,
+    };
+
+    /// Every triangle as its corners' position, normal and texture coordinates.
+    fn corners(mesh: &Mesh, indices: &[u32]) -> Vec<([f32; 3], [f32; 3], [f32; 2])> {
+        indices
+            .iter()
+            .map(|&i| {
+                let v = &mesh.vertices[i as usize];
+                (v.position, v.normal, v.tex_coords)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn model_round_trips_through_obj_loader() {
+        let dir = TempDir::new("obj_exporter_round_trip");
+        let path = dir.join("cube.obj");
+
+        let mut model = Model::placeholder();
+        model.mesh.vertices[0].position = [-1. / 3., 0.1, 1e-7];
+        model.submeshes = vec![
+            SubMesh {
+                name: "top".to_owned(),
+                indices: 0..12,
+                material: Some(1),
+            },
+            // must not pick up the material of the previous submesh
+            SubMesh {
+                name: "sides".to_owned(),
+                indices: 12..24,
+                material: None,
+            },
+            SubMesh {
+                name: "bottom".to_owned(),
+                indices: 24..36,
+                material: Some(0),
+            },
+        ];
+        model.materials = vec![
+            Material {
+                name: "plain".to_owned(),
+                ..Default::default()
+            },
+            Material {
+                name: "shiny".to_owned(),
+                diffuse: [0.1, 0.2, 0.3],
+                shininess: 96.,
+                dissolve: 0.5,
+                metallic: 0.25,
+                diffuse_texture: Some(dir.join("shiny.png")),
+                ..Default::default()
+            },
+        ];
+        save_obj(&path, &model).unwrap();
+
+        let options = ObjLoadOptions {
+            cache: false,
+            ..Default::default()
+        };
+        let loaded = try_load_model_with(&path, &options).unwrap();
+        assert_eq!(loaded.materials.len(), 3);
+        assert_eq!(loaded.submeshes.len(), 3);
+        for (a, b) in model.submeshes.iter().zip(&loaded.submeshes) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(
+                corners(&model.mesh, model.submesh_indices(a)),
+                corners(&loaded.mesh, loaded.submesh_indices(b))
+            );
+            let default = Material {
+                name: "default".to_owned(),
+                ..Default::default()
+            };
+            let (a, b) = (
+                model.material(a).unwrap_or(&default),
+                loaded.material(b).unwrap(),
+            );
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.diffuse, b.diffuse);
+            assert_eq!(a.shininess, b.shininess);
+            assert_eq!(a.dissolve, b.dissolve);
+            assert_eq!(a.metallic, b.metallic);
+            assert_eq!(a.diffuse_texture, b.diffuse_texture);
+        }
+    }
+
+    #[test]
+    fn bare_mesh_has_no_mtl() {
+        let mesh = Mesh {
+            vertices: vec![
+                Vertex::new([0., 0., 0.], [0.; 3]),
+                Vertex::new([1., 0., 0.], [0.; 3]),
+                Vertex::new([0., 1., 0.], [0.; 3]),
+            ],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        let mut text = Vec::new();
+        write_obj(&mut text, &Model::from(mesh), None).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("v 1 0 0\n"));
+        assert!(text.ends_with("g submesh0\nusemtl default\nf 1 2 3\n"));
+        assert!(!text.contains("mtllib") && !text.contains("vn"));
+    }
+}
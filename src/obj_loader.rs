@@ -1,75 +1,876 @@
-use std::{fmt::Debug, path::Path};
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt::{self, Debug},
+    fs,
+    hash::Hasher,
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use glium::implement_vertex;
+pub use crate::mesh::{Material, Mesh, Model, NormalMode, SubMesh, WeldOptions};
+use crate::{
+    mesh::{LoadStats, NormalWeighting, Vertex},
+    mesh_cache::{self, CacheKey, Fnv1a, MeshCacheError},
+};
 
-#[derive(Clone, Copy, Debug)]
-pub struct ObjVertex {
-    position: [f32; 3],
-    normal: [f32; 3],
+#[derive(Clone, Debug)]
+pub struct ObjLoadOptions {
+    /// Passed to tobj, `single_index` must be set.
+    pub tobj: tobj::LoadOptions,
+    /// How normals are generated for objects that have none.
+    pub normals: NormalMode,
+    /// Regenerate normals even for objects that come with their own.
+    pub force_normals: bool,
+    /// Merge duplicate vertices and drop degenerate triangles after loading.
+    /// Off by default, so the vertex and index counts follow the file.
+    pub weld: Option<WeldOptions>,
+    /// Read and write a [`mesh_cache`] file next to the OBJ.
+    pub cache: bool,
 }
 
-implement_vertex!(ObjVertex, position, normal);
+impl Default for ObjLoadOptions {
+    fn default() -> Self {
+        ObjLoadOptions {
+            tobj: tobj::GPU_LOAD_OPTIONS,
+            normals: NormalMode::default(),
+            force_normals: false,
+            weld: None,
+            cache: true,
+        }
+    }
+}
+
+impl ObjLoadOptions {
+    /// Hash of the options that shape the model, for [`CacheKey::options`].
+    /// Fields are hashed one by one in a fixed order, so the hash only changes
+    /// along with their values.
+    pub fn cache_hash(&self) -> u64 {
+        let ObjLoadOptions {
+            tobj,
+            normals,
+            force_normals,
+            weld,
+            // where the model comes from, not what it is
+            cache: _,
+        } = self;
+        let mut hasher = Fnv1a::default();
+        for flag in [
+            tobj.single_index,
+            tobj.triangulate,
+            tobj.ignore_points,
+            tobj.ignore_lines,
+            *force_normals,
+        ] {
+            hasher.write_u8(flag as u8);
+        }
+        let float = |hasher: &mut Fnv1a, f: f32| hasher.write(&f.to_le_bytes());
+        match *normals {
+            NormalMode::Flat => hasher.write_u8(0),
+            NormalMode::Smooth {
+                weighting,
+                crease_angle,
+            } => {
+                hasher.write_u8(1);
+                hasher.write_u8(match weighting {
+                    NormalWeighting::Area => 0,
+                    NormalWeighting::Angle => 1,
+                });
+                float(&mut hasher, crease_angle);
+            }
+        }
+        match weld {
+            None => hasher.write_u8(0),
+            Some(weld) => {
+                hasher.write_u8(1);
+                for epsilon in [
+                    weld.position_epsilon,
+                    weld.normal_epsilon,
+                    weld.tex_coord_epsilon,
+                    weld.color_epsilon,
+                ] {
+                    float(&mut hasher, epsilon);
+                }
+            }
+        }
+        hasher.finish()
+    }
+}
 
-pub fn load_obj<P>(path: P) -> (Vec<ObjVertex>, Vec<u32>)
+#[derive(Debug)]
+pub enum ObjLoadError {
+    /// The OBJ file could not be opened or read.
+    Io { path: PathBuf, source: io::Error },
+    /// tobj rejected the file, `line` is the last line it read before failing.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        source: tobj::LoadError,
+    },
+    /// An attribute array does not line up with the positions of the model.
+    AttributeCountMismatch {
+        model: String,
+        attribute: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    /// A face is not a triangle, only possible when loading without triangulation.
+    NonTriangularFace {
+        model: String,
+        face: usize,
+        vertex_count: usize,
+    },
+    /// The file is a Git LFS pointer, the real object was never fetched.
+    LfsPointer {
+        path: PathBuf,
+        oid: String,
+        size: u64,
+    },
+}
+
+impl fmt::Display for ObjLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjLoadError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            ObjLoadError::Parse { path, line, source } => {
+                write!(f, "{}:{}: {}", path.display(), line, source)
+            }
+            ObjLoadError::AttributeCountMismatch {
+                model,
+                attribute,
+                expected,
+                found,
+            } => write!(
+                f,
+                "model {:?} has {} {} values, expected {}",
+                model, found, attribute, expected
+            ),
+            ObjLoadError::NonTriangularFace {
+                model,
+                face,
+                vertex_count,
+            } => write!(
+                f,
+                "face {} of model {:?} has {} vertices, only triangles are supported",
+                face, model, vertex_count
+            ),
+            ObjLoadError::LfsPointer { path, oid, size } => write!(
+                f,
+                "{} is a Git LFS pointer to {} ({} bytes), run `git lfs pull` to fetch it",
+                path.display(),
+                oid,
+                size
+            ),
+        }
+    }
+}
+
+impl Error for ObjLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjLoadError::Io { source, .. } => Some(source),
+            ObjLoadError::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Load an OBJ file, panicking on any error. Meant for the examples, use
+/// [`try_load_obj`] when a bad asset should not take the process down.
+pub fn load_obj<P>(path: P) -> Mesh
 where
     P: AsRef<Path> + Debug,
 {
-    let (models, _) = tobj::load_obj(&path, &tobj::GPU_LOAD_OPTIONS)
-        .unwrap_or_else(|_| panic!("Failed to load OBJ file: {:?}", path.as_ref()));
+    try_load_obj(&path).unwrap_or_else(|e| panic!("Failed to load OBJ file {:?}: {}", path, e))
+}
 
-    let mut vertices = Vec::new();
-    let mut indices = Vec::new();
+/// Like [`load_obj`], but falls back to [`Mesh::placeholder`] when the file is
+/// a Git LFS pointer so the examples still start without the real assets.
+pub fn load_obj_or_placeholder<P>(path: P) -> Mesh
+where
+    P: AsRef<Path> + Debug,
+{
+    load_model_or_placeholder(path).mesh
+}
 
-    for model in models {
-        let mesh = &model.mesh;
-        println!("Uploading model: {}", model.name);
-        assert!(mesh.positions.len() % 3 == 0);
-        assert!(mesh.normals.len() % 3 == 0);
-        assert!(mesh.indices.len() % 3 == 0);
+/// Load an OBJ file with its materials, falling back to
+/// [`Model::placeholder`] for Git LFS pointers and panicking on other errors.
+pub fn load_model_or_placeholder<P>(path: P) -> Model
+where
+    P: AsRef<Path> + Debug,
+{
+    match try_load_model(&path) {
+        Ok(model) => model,
+        Err(e @ ObjLoadError::LfsPointer { .. }) => {
+            log::warn!("{}, using a placeholder mesh", e);
+            Model::placeholder()
+        }
+        Err(e) => panic!("Failed to load OBJ file {:?}: {}", path, e),
+    }
+}
 
-        let new_indices = mesh
-            .indices
-            .iter()
-            .map(|i| i + vertices.len() as u32)
-            .collect::<Vec<_>>();
-
-        let n = mesh.positions.len() / 3;
-        if mesh.normals.is_empty() {
-            for i in 0..n {
-                let position = [
-                    mesh.positions[3 * i],
-                    mesh.positions[3 * i + 1],
-                    mesh.positions[3 * i + 2],
-                ];
-                vertices.push(ObjVertex {
-                    position,
-                    normal: [0.; 3],
-                })
-            }
+pub fn try_load_obj<P>(path: P) -> Result<Mesh, ObjLoadError>
+where
+    P: AsRef<Path>,
+{
+    try_load_obj_with(path, &ObjLoadOptions::default())
+}
+
+/// Load an OBJ file with custom options. Faces are checked to be triangles
+/// when tobj's `triangulate` is off.
+pub fn try_load_obj_with<P>(path: P, options: &ObjLoadOptions) -> Result<Mesh, ObjLoadError>
+where
+    P: AsRef<Path>,
+{
+    try_load_model_with(path, options).map(|model| model.mesh)
+}
+
+pub fn try_load_model<P>(path: P) -> Result<Model, ObjLoadError>
+where
+    P: AsRef<Path>,
+{
+    try_load_model_with(path, &ObjLoadOptions::default())
+}
+
+/// Load an OBJ file into one submesh per OBJ object, along with the materials
+/// of the MTL libraries it references.
+///
+/// With [`ObjLoadOptions::cache`] set, the model is read from the cache next to
+/// the file when the OBJ, its MTL libraries and the options are unchanged since
+/// the cache was written, and the cache is rewritten otherwise.
+pub fn try_load_model_with<P>(path: P, options: &ObjLoadOptions) -> Result<Model, ObjLoadError>
+where
+    P: AsRef<Path>,
+{
+    try_load_model_with_progress(path, options, |_| {})
+}
+
+/// [`try_load_model_with`] calling `progress` with the fraction of the file
+/// parsed so far, at most once per percent and with 1 once done.
+pub fn try_load_model_with_progress<P, F>(
+    path: P,
+    options: &ObjLoadOptions,
+    mut progress: F,
+) -> Result<Model, ObjLoadError>
+where
+    P: AsRef<Path>,
+    F: FnMut(f32),
+{
+    let start = Instant::now();
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| ObjLoadError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let reader = ProgressReader {
+        inner: &bytes[..],
+        total: bytes.len(),
+        read: 0,
+        reported: 0,
+        progress: &mut progress,
+    };
+    if !options.cache {
+        let model = load_obj_buf(reader, path, options).map(|(model, _)| model)?;
+        log_loaded(path, &model, start);
+        progress(1.);
+        return Ok(model);
+    }
+
+    let key = CacheKey {
+        source: mesh_cache::fnv1a(&bytes),
+        options: options.cache_hash(),
+    };
+    match mesh_cache::read_cache(path, &key) {
+        Ok((model, _)) => {
+            log::debug!("Read mesh cache {}", mesh_cache::cache_path(path).display());
+            log_loaded(path, &model, start);
+            progress(1.);
+            return Ok(model);
+        }
+        Err(MeshCacheError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {}
+        Err(MeshCacheError::Stale) => log::debug!("Mesh cache of {} is stale", path.display()),
+        Err(e) => log::warn!("Ignoring mesh cache of {}: {}", path.display(), e),
+    }
+
+    let (model, mtl_libs) = load_obj_buf(reader, path, options)?;
+    // a read-only asset directory only costs the next run a re-parse
+    if let Err(e) = mesh_cache::write_cache(path, &key, &mtl_libs, &model) {
+        log::warn!("Failed to write mesh cache of {}: {}", path.display(), e);
+    }
+    log_loaded(path, &model, start);
+    progress(1.);
+    Ok(model)
+}
+
+/// Returns the model and the MTL libraries it was built from.
+fn load_obj_buf<R>(
+    mut reader: R,
+    path: &Path,
+    options: &ObjLoadOptions,
+) -> Result<(Model, Vec<PathBuf>), ObjLoadError>
+where
+    R: BufRead,
+{
+    let io_error = |source| ObjLoadError::Io {
+        path: path.to_owned(),
+        source,
+    };
+    if reader
+        .fill_buf()
+        .map_err(io_error)?
+        .starts_with(LFS_POINTER_HEADER)
+    {
+        let mut pointer = String::new();
+        reader.read_to_string(&mut pointer).map_err(io_error)?;
+        if let Some((oid, size)) = parse_lfs_pointer(&pointer) {
+            return Err(ObjLoadError::LfsPointer {
+                path: path.to_owned(),
+                oid,
+                size,
+            });
+        }
+        return parse_obj_buf(pointer.as_bytes(), path, options);
+    }
+    parse_obj_buf(reader, path, options)
+}
+
+fn parse_obj_buf<R>(
+    reader: R,
+    path: &Path,
+    options: &ObjLoadOptions,
+) -> Result<(Model, Vec<PathBuf>), ObjLoadError>
+where
+    R: BufRead,
+{
+    let mut reader = LineCounter {
+        inner: reader,
+        line: 0,
+    };
+    let mtl_dir = path.parent().unwrap_or(Path::new(""));
+    let mtl_libs = RefCell::new(Vec::new());
+    let (models, materials) = tobj::load_obj_buf(&mut reader, &options.tobj, |p| {
+        let p = mtl_dir.join(p);
+        mtl_libs.borrow_mut().push(p.clone());
+        tobj::load_mtl(p)
+    })
+    .map_err(|source| ObjLoadError::Parse {
+        path: path.to_owned(),
+        line: reader.line,
+        source,
+    })?;
+
+    let materials = match materials {
+        Ok(materials) => materials
+            .into_iter()
+            .map(|m| convert_material(m, mtl_dir))
+            .collect(),
+        Err(e) => {
+            log::warn!("Failed to load materials of {}: {}", path.display(), e);
+            Vec::new()
+        }
+    };
+
+    let mut model = Model {
+        materials,
+        ..Default::default()
+    };
+    for m in models {
+        let start = model.mesh.indices.len() as u32;
+        append_model(&mut model.mesh, &m, options)?;
+        log::debug!(
+            "Object {:?}: {} vertices, {} indices",
+            m.name,
+            m.mesh.positions.len() / 3,
+            m.mesh.indices.len()
+        );
+        model.submeshes.push(SubMesh {
+            name: m.name,
+            indices: start..model.mesh.indices.len() as u32,
+            material: m.mesh.material_id.filter(|&i| i < model.materials.len()),
+        });
+    }
+    if let Some(weld) = &options.weld {
+        let report = model.weld(weld);
+        log::debug!(
+            "Welded {} vertices, removed {} degenerate triangles",
+            report.merged_vertices,
+            report.degenerate_triangles
+        );
+    }
+    Ok((model, mtl_libs.into_inner()))
+}
+
+fn convert_material(m: tobj::Material, dir: &Path) -> Material {
+    let default = Material::default();
+    let texture = |t: Option<String>| t.map(|t| dir.join(t));
+    let pbr = |key| m.unknown_param.get(key).and_then(|v| v.trim().parse().ok());
+    Material {
+        name: m.name,
+        ambient: m.ambient.unwrap_or(default.ambient),
+        diffuse: m.diffuse.unwrap_or(default.diffuse),
+        specular: m.specular.unwrap_or(default.specular),
+        shininess: m.shininess.unwrap_or(default.shininess),
+        dissolve: m.dissolve.unwrap_or(default.dissolve),
+        metallic: pbr("Pm").unwrap_or(default.metallic),
+        roughness: pbr("Pr").unwrap_or(default.roughness),
+        diffuse_texture: texture(m.diffuse_texture),
+        normal_texture: texture(m.normal_texture),
+        specular_texture: texture(m.specular_texture),
+        ..default
+    }
+}
+
+fn append_model(
+    mesh: &mut Mesh,
+    model: &tobj::Model,
+    options: &ObjLoadOptions,
+) -> Result<(), ObjLoadError> {
+    let m = &model.mesh;
+    let mismatch = |attribute, expected, found| ObjLoadError::AttributeCountMismatch {
+        model: model.name.clone(),
+        attribute,
+        expected,
+        found,
+    };
+
+    if m.positions.len() % 3 != 0 {
+        return Err(mismatch(
+            "position",
+            m.positions.len() / 3 * 3,
+            m.positions.len(),
+        ));
+    }
+    if !m.normals.is_empty() && m.normals.len() != m.positions.len() {
+        return Err(mismatch("normal", m.positions.len(), m.normals.len()));
+    }
+    if !m.texcoords.is_empty() && m.texcoords.len() != m.positions.len() / 3 * 2 {
+        return Err(mismatch(
+            "texcoord",
+            m.positions.len() / 3 * 2,
+            m.texcoords.len(),
+        ));
+    }
+    if let Some((face, &n)) = m.face_arities.iter().enumerate().find(|(_, &n)| n != 3) {
+        return Err(ObjLoadError::NonTriangularFace {
+            model: model.name.clone(),
+            face,
+            vertex_count: n as usize,
+        });
+    }
+    if m.indices.len() % 3 != 0 {
+        return Err(ObjLoadError::NonTriangularFace {
+            model: model.name.clone(),
+            face: m.indices.len() / 3,
+            vertex_count: m.indices.len() % 3,
+        });
+    }
+
+    let n = m.positions.len() / 3;
+    let mut part = Mesh {
+        indices: m.indices.clone(),
+        ..Default::default()
+    };
+    for i in 0..n {
+        let position = [
+            m.positions[3 * i],
+            m.positions[3 * i + 1],
+            m.positions[3 * i + 2],
+        ];
+        let normal = if m.normals.is_empty() {
+            [0.; 3]
+        } else {
+            [m.normals[3 * i], m.normals[3 * i + 1], m.normals[3 * i + 2]]
+        };
+        let tex_coords = if m.texcoords.is_empty() {
+            [0.; 2]
         } else {
-            assert!(mesh.normals.len() == mesh.positions.len());
-            for i in 0..n {
-                let position = [
-                    mesh.positions[3 * i],
-                    mesh.positions[3 * i + 1],
-                    mesh.positions[3 * i + 2],
-                ];
-                let normal = [
-                    mesh.normals[3 * i],
-                    mesh.normals[3 * i + 1],
-                    mesh.normals[3 * i + 2],
-                ];
-                vertices.push(ObjVertex { position, normal })
+            [m.texcoords[2 * i], m.texcoords[2 * i + 1]]
+        };
+        part.vertices
+            .push(Vertex::new(position, normal).with_tex_coords(tex_coords));
+    }
+    part.attributes.normals = !m.normals.is_empty();
+    part.attributes.tex_coords = !m.texcoords.is_empty();
+    if !part.attributes.normals || options.force_normals {
+        part.generate_normals(options.normals);
+    }
+    // before appending, so parts without texture coordinates do not turn
+    // them off for the whole model
+    if part.attributes.tex_coords {
+        part.generate_tangents();
+    }
+
+    mesh.append(part);
+    Ok(())
+}
+
+fn log_loaded(path: &Path, model: &Model, start: Instant) {
+    log::info!(
+        "Loaded {}: {}",
+        path.display(),
+        LoadStats::new(model, start.elapsed())
+    );
+}
+
+const LFS_POINTER_HEADER: &[u8] = b"version https://git-lfs.github.com/spec/v1";
+
+/// Returns the `oid` and `size` of a Git LFS pointer file.
+fn parse_lfs_pointer(text: &str) -> Option<(String, u64)> {
+    let mut oid = None;
+    let mut size = None;
+    for line in text.lines() {
+        match line.split_once(' ') {
+            Some(("oid", value)) => oid = Some(value.trim().to_owned()),
+            Some(("size", value)) => size = value.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    Some((oid?, size?))
+}
+
+/// Counts the lines tobj has consumed so parse errors can point at one.
+struct LineCounter<R> {
+    inner: R,
+    line: usize,
+}
+
+impl<R: io::Read> io::Read for LineCounter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: BufRead> BufRead for LineCounter<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            self.line += buf[..amt.min(buf.len())]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count();
+        }
+        self.inner.consume(amt)
+    }
+}
+
+/// Reports how much of the input tobj has consumed.
+struct ProgressReader<'a, R> {
+    inner: R,
+    total: usize,
+    read: usize,
+    /// last reported percentage
+    reported: usize,
+    progress: &'a mut dyn FnMut(f32),
+}
+
+impl<R: io::Read> io::Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: BufRead> BufRead for ProgressReader<'_, R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.read += amt;
+        let percent = self.read * 100 / self.total.max(1);
+        // 100% is left for when the model is fully built
+        if percent > self.reported && percent < 100 {
+            self.reported = percent;
+            (self.progress)(self.read as f32 / self.total as f32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec3, Vec4};
+
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn parse(src: &str, options: &ObjLoadOptions) -> Result<Mesh, ObjLoadError> {
+        load_obj_buf(src.as_bytes(), Path::new("test.obj"), options).map(|(model, _)| model.mesh)
+    }
+
+    #[test]
+    fn parse_error_reports_line() {
+        let src = "v 0 0 0\nv 1 0 0\nv 0 x 0\nf 1 2 3\n";
+        match parse(src, &ObjLoadOptions::default()) {
+            Err(ObjLoadError::Parse { line, .. }) => assert_eq!(line, 3),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn quads_are_rejected_without_triangulation() {
+        let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n";
+        let options = ObjLoadOptions {
+            tobj: tobj::LoadOptions {
+                single_index: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse(src, &options),
+            Err(ObjLoadError::NonTriangularFace {
+                vertex_count: 4,
+                ..
+            })
+        ));
+        assert_eq!(
+            parse(src, &ObjLoadOptions::default())
+                .unwrap()
+                .indices
+                .len(),
+            6
+        );
+    }
+
+    #[test]
+    fn lfs_pointer_is_detected() {
+        let src = "version https://git-lfs.github.com/spec/v1\n\
+                   oid sha256:8233327978e90e146669422679ce61efc642d796a1701412bb38a3b5c260371c\n\
+                   size 4141885\n";
+        match parse(src, &ObjLoadOptions::default()) {
+            Err(ObjLoadError::LfsPointer { oid, size, .. }) => {
+                assert!(oid.starts_with("sha256:8233"));
+                assert_eq!(size, 4141885);
             }
+            other => panic!("unexpected result {:?}", other),
         }
+    }
 
-        indices = [indices, new_indices].concat();
+    #[test]
+    fn tex_coords_produce_tangents() {
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\n\
+                   vn 0 0 1\nf 1/1/1 2/2/1 3/3/1\n";
+        let mesh = parse(src, &ObjLoadOptions::default()).unwrap();
+        assert!(mesh.attributes.tex_coords && mesh.attributes.tangents);
+        assert_eq!(mesh.vertices[1].tex_coords, [1., 0.]);
+        assert_eq!(mesh.vertices[0].tangent, [1., 0., 0., 1.]);
     }
 
-    println!(
-        "Load OBJ successfully, vertex count: {}, index count: {}",
-        vertices.len(),
-        indices.len()
-    );
-    (vertices, indices)
+    #[test]
+    fn objects_without_tex_coords_keep_tangents_of_the_others() {
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 0 1\nvt 1 0\n\
+                   vn 0 0 1\no uv\nf 1/1/1 2/2/1 3/3/1\no plain\nf 1//1 3//1 2//1\n";
+        let mesh = parse(src, &ObjLoadOptions::default()).unwrap();
+        assert!(!mesh.attributes.tex_coords && mesh.attributes.tangents);
+        // +X runs along V in the first object
+        assert_eq!(mesh.vertices[0].tangent, [0., 1., 0., -1.]);
+        for v in &mesh.vertices {
+            let normal = Vec3::from(v.normal);
+            assert!(Vec4::from(v.tangent).truncate().dot(normal).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn missing_normals_are_generated() {
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+        let mesh = parse(src, &ObjLoadOptions::default()).unwrap();
+        assert!(mesh.attributes.normals);
+        assert!(mesh.vertices.iter().all(|v| v.normal == [0., 0., 1.]));
+
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 1 0 0\nf 1//1 2//1 3//1\n";
+        let mesh = parse(src, &ObjLoadOptions::default()).unwrap();
+        assert_eq!(mesh.vertices[0].normal, [1., 0., 0.]);
+        let options = ObjLoadOptions {
+            force_normals: true,
+            ..Default::default()
+        };
+        let mesh = parse(src, &options).unwrap();
+        assert_eq!(mesh.vertices[0].normal, [0., 0., 1.]);
+    }
+
+    #[test]
+    fn objects_become_submeshes_with_materials() {
+        let dir = TempDir::new("obj_loader_materials");
+        std::fs::write(
+            dir.join("parts.mtl"),
+            "newmtl red\nKd 1 0 0\nNs 32\nmap_Kd red.png\n\
+             newmtl blue\nKd 0 0 1\nd 0.5\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("parts.obj"),
+            "mtllib parts.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\n\
+             o body\nusemtl red\nf 1 2 3\no window\nusemtl blue\nf 2 4 3\n",
+        )
+        .unwrap();
+
+        let model = try_load_model(dir.join("parts.obj")).unwrap();
+        assert_eq!(model.submeshes.len(), 2);
+        assert_eq!(model.submeshes[0].name, "body");
+        assert_eq!(model.submeshes[1].indices, 3..6);
+
+        let red = model.material(&model.submeshes[0]).unwrap();
+        assert_eq!(red.diffuse, [1., 0., 0.]);
+        assert_eq!(red.shininess, 32.);
+        assert_eq!(red.diffuse_texture, Some(dir.join("red.png")));
+        let blue = model.material(&model.submeshes[1]).unwrap();
+        assert_eq!(blue.dissolve, 0.5);
+    }
+
+    #[test]
+    fn welding_is_opt_in() {
+        // the same position twice
+        let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 0 0\nf 1 2 3\nf 3 4 1\n";
+        let mesh = parse(src, &ObjLoadOptions::default()).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        let options = ObjLoadOptions {
+            weld: Some(WeldOptions::default()),
+            ..Default::default()
+        };
+        let mesh = parse(src, &options).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+    }
+
+    #[test]
+    fn cache_hash_follows_the_option_values() {
+        let defaults = ObjLoadOptions::default();
+        // fixed so reformatting the options cannot silently change it
+        assert_eq!(defaults.cache_hash(), 0x9fd7_1e35_6965_1b40);
+        let uncached = ObjLoadOptions {
+            cache: false,
+            ..Default::default()
+        };
+        assert_eq!(uncached.cache_hash(), defaults.cache_hash());
+        let weld = WeldOptions {
+            color_epsilon: 0.1,
+            ..Default::default()
+        };
+        let welded = ObjLoadOptions {
+            weld: Some(weld),
+            ..Default::default()
+        };
+        assert_ne!(welded.cache_hash(), defaults.cache_hash());
+    }
+
+    #[test]
+    fn cache_is_written_and_invalidated() {
+        let dir = TempDir::new("obj_loader_cache");
+        let path = dir.join("triangle.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+
+        let parsed = try_load_model(&path).unwrap();
+        assert!(mesh_cache::cache_path(&path).exists());
+        let cached = try_load_model(&path).unwrap();
+        assert_eq!(cached.mesh, parsed.mesh);
+        assert_eq!(cached.submeshes, parsed.submeshes);
+
+        std::fs::write(&path, "v 0 0 0\nv 2 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let edited = try_load_model(&path).unwrap();
+        assert_eq!(edited.mesh.vertices[1].position, [2., 0., 0.]);
+
+        // other options must not pick up a cache written for the defaults
+        let options = ObjLoadOptions {
+            normals: NormalMode::Flat,
+            force_normals: true,
+            ..Default::default()
+        };
+        let key = |options: &ObjLoadOptions| CacheKey {
+            source: mesh_cache::fnv1a(&std::fs::read(&path).unwrap()),
+            options: options.cache_hash(),
+        };
+        assert!(mesh_cache::read_cache(&path, &key(&ObjLoadOptions::default())).is_ok());
+        assert!(matches!(
+            mesh_cache::read_cache(&path, &key(&options)),
+            Err(MeshCacheError::Stale)
+        ));
+    }
+
+    #[test]
+    fn missing_material_libraries_are_cached_until_they_appear() {
+        let dir = TempDir::new("obj_loader_missing_mtl");
+        let path = dir.join("triangle.obj");
+        let mtl = dir.join("missing.mtl");
+        std::fs::write(
+            &path,
+            "mtllib missing.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n",
+        )
+        .unwrap();
+
+        let parsed = try_load_model(&path).unwrap();
+        assert!(parsed.materials.is_empty());
+        let key = CacheKey {
+            source: mesh_cache::fnv1a(&std::fs::read(&path).unwrap()),
+            options: ObjLoadOptions::default().cache_hash(),
+        };
+        assert!(mesh_cache::read_cache(&path, &key).is_ok());
+
+        std::fs::write(&mtl, "newmtl red\nKd 1 0 0\n").unwrap();
+        assert!(matches!(
+            mesh_cache::read_cache(&path, &key),
+            Err(MeshCacheError::Stale)
+        ));
+        let model = try_load_model(&path).unwrap();
+        assert_eq!(model.materials[0].diffuse, [1., 0., 0.]);
+    }
+
+    /// Keeps every log message so tests can look for theirs.
+    struct CaptureLogger;
+
+    static LOGS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let message = format!("{} {}", record.level(), record.args());
+            LOGS.lock().unwrap().push(message);
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn load_stats_are_logged() {
+        static LOGGER: CaptureLogger = CaptureLogger;
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let dir = TempDir::new("obj_loader_stats");
+        let path = dir.join("stats.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\no tri\nf 1 2 3\n").unwrap();
+        let options = ObjLoadOptions {
+            cache: false,
+            ..Default::default()
+        };
+        try_load_model_with(&path, &options).unwrap();
+
+        let logs = LOGS.lock().unwrap();
+        let loaded = format!("INFO Loaded {}: ", path.display());
+        let stats = logs
+            .iter()
+            .find_map(|m| m.strip_prefix(&loaded))
+            .expect("load stats are logged");
+        assert!(
+            stats.starts_with("3 vertices, 3 indices, 1 submeshes, 0 materials in "),
+            "{}",
+            stats
+        );
+        assert!(logs.contains(&"DEBUG Object \"tri\": 3 vertices, 3 indices".to_owned()));
+    }
+
+    #[test]
+    fn missing_file_is_io_error() {
+        assert!(matches!(
+            try_load_obj("does/not/exist.obj"),
+            Err(ObjLoadError::Io { .. })
+        ));
+    }
 }
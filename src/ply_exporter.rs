@@ -0,0 +1,121 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::mesh::Mesh;
+
+/// Write `mesh` to a binary little endian PLY file with the attributes it
+/// carries: positions, normals, texture coordinates as `u`/`v` and colours as
+/// `uchar` RGBA.
+pub fn save_ply<P>(path: P, mesh: &Mesh) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let mut w = BufWriter::new(File::create(path)?);
+    write_ply(&mut w, mesh)?;
+    w.flush()
+}
+
+pub fn write_ply<W>(mut w: W, mesh: &Mesh) -> io::Result<()>
+where
+    W: Write,
+{
+    let attributes = mesh.attributes;
+    let mut header = String::from("ply\nformat binary_little_endian 1.0\n");
+    header += &format!("element vertex {}\n", mesh.vertices.len());
+    let mut properties = vec!["x", "y", "z"];
+    if attributes.normals {
+        properties.extend(["nx", "ny", "nz"]);
+    }
+    if attributes.tex_coords {
+        properties.extend(["u", "v"]);
+    }
+    for p in properties {
+        header += &format!("property float {}\n", p);
+    }
+    if attributes.colors {
+        for p in ["red", "green", "blue", "alpha"] {
+            header += &format!("property uchar {}\n", p);
+        }
+    }
+    header += &format!("element face {}\n", mesh.indices.len() / 3);
+    header += "property list uchar uint vertex_indices\nend_header\n";
+    w.write_all(header.as_bytes())?;
+
+    let mut body = Vec::with_capacity(mesh.vertices.len() * 36 + mesh.indices.len() / 3 * 13);
+    let floats = |body: &mut Vec<u8>, values: &[f32]| {
+        values
+            .iter()
+            .for_each(|v| body.extend_from_slice(&v.to_le_bytes()))
+    };
+    for v in &mesh.vertices {
+        floats(&mut body, &v.position);
+        if attributes.normals {
+            floats(&mut body, &v.normal);
+        }
+        if attributes.tex_coords {
+            floats(&mut body, &v.tex_coords);
+        }
+        if attributes.colors {
+            body.extend(v.color.map(|c| (c.clamp(0., 1.) * 255.).round() as u8));
+        }
+    }
+    for tri in mesh.indices.chunks_exact(3) {
+        body.push(3);
+        tri.iter()
+            .for_each(|i| body.extend_from_slice(&i.to_le_bytes()));
+    }
+    w.write_all(&body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mesh::Vertex, ply_loader::try_load_ply, test_util::TempDir};
+
+    #[test]
+    fn mesh_round_trips_through_ply_loader() {
+        let dir = TempDir::new("ply_exporter_round_trip");
+        let path = dir.join("cube.ply");
+
+        let mut mesh = Mesh::placeholder();
+        mesh.attributes.colors = true;
+        for (i, v) in mesh.vertices.iter_mut().enumerate() {
+            v.color = [i as f32 / 23., 0.5, 1., 1.];
+        }
+        save_ply(&path, &mesh).unwrap();
+
+        let loaded = try_load_ply(&path).unwrap();
+        assert_eq!(loaded.indices, mesh.indices);
+        assert!(loaded.attributes.normals && loaded.attributes.tex_coords);
+        assert!(loaded.attributes.colors);
+        for (a, b) in mesh.vertices.iter().zip(&loaded.vertices) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.normal, b.normal);
+            assert_eq!(a.tex_coords, b.tex_coords);
+            for (a, b) in a.color.iter().zip(b.color) {
+                assert!((a - b).abs() <= 1. / 255.);
+            }
+        }
+    }
+
+    #[test]
+    fn header_lists_only_present_attributes() {
+        let mesh = Mesh {
+            vertices: vec![Vertex::new([0., 0., 0.], [0.; 3]); 3],
+            indices: vec![0, 1, 2],
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        write_ply(&mut bytes, &mesh).unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("element vertex 3\nproperty float x\n"));
+        assert!(text.contains("property float z\nelement face 1\n"));
+        assert_eq!(
+            bytes.len(),
+            text.find("end_header\n").unwrap() + 11 + 3 * 12 + 13
+        );
+    }
+}
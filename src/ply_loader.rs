@@ -0,0 +1,549 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug},
+    fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::mesh::{LoadStats, Mesh, NormalMode, Vertex};
+
+#[derive(Debug)]
+pub enum PlyLoadError {
+    /// The PLY file could not be opened or read.
+    Io { path: PathBuf, source: io::Error },
+    /// The header is malformed, `line` counts from 1.
+    Header { line: usize, message: String },
+    /// The body ended early or holds a value that does not parse.
+    Data { element: String, message: String },
+    /// There is no `vertex` element with `x`, `y` and `z` properties.
+    MissingPositions,
+}
+
+impl fmt::Display for PlyLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyLoadError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            PlyLoadError::Header { line, message } => {
+                write!(f, "invalid PLY header at line {}: {}", line, message)
+            }
+            PlyLoadError::Data { element, message } => {
+                write!(f, "invalid {} data: {}", element, message)
+            }
+            PlyLoadError::MissingPositions => write!(f, "PLY file has no vertex positions"),
+        }
+    }
+}
+
+impl Error for PlyLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlyLoadError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Load a PLY file, panicking on any error. Meant for the examples, use
+/// [`try_load_ply`] otherwise.
+pub fn load_ply<P>(path: P) -> Mesh
+where
+    P: AsRef<Path> + Debug,
+{
+    try_load_ply(&path).unwrap_or_else(|e| panic!("Failed to load PLY file {:?}: {}", path, e))
+}
+
+/// Load an ASCII or binary (little or big endian) PLY file. Positions,
+/// normals, texture coordinates and colours of the `vertex` element are read,
+/// polygons of the `face` element are triangulated as fans.
+pub fn try_load_ply<P>(path: P) -> Result<Mesh, PlyLoadError>
+where
+    P: AsRef<Path>,
+{
+    let start = Instant::now();
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| PlyLoadError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let mesh = parse_ply(&bytes)?;
+    log::info!(
+        "Loaded {}: {}",
+        path.display(),
+        LoadStats::of_mesh(&mesh, start.elapsed())
+    );
+    Ok(mesh)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Scale that maps the integer range to [0, 1], used for colours.
+    fn unit_scale(self) -> f64 {
+        match self {
+            Scalar::I8 => i8::MAX as f64,
+            Scalar::U8 => u8::MAX as f64,
+            Scalar::I16 => i16::MAX as f64,
+            Scalar::U16 => u16::MAX as f64,
+            Scalar::I32 => i32::MAX as f64,
+            Scalar::U32 => u32::MAX as f64,
+            Scalar::F32 | Scalar::F64 => 1.,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Property {
+    Scalar(Scalar, String),
+    List(Scalar, Scalar, String),
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar(_, name) | Property::List(_, _, name) => name,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    /// Fewest bytes one item of the element takes in the body: a digit and a
+    /// separator per ASCII value, the size of the scalar or of the list count
+    /// in binary.
+    fn min_size(&self, format: Format) -> usize {
+        self.properties
+            .iter()
+            .map(|p| match (format, p) {
+                (Format::Ascii, _) => 2,
+                (_, Property::Scalar(scalar, _)) | (_, Property::List(scalar, _, _)) => {
+                    scalar.size()
+                }
+            })
+            .sum()
+    }
+}
+
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize), PlyLoadError> {
+    let header_error = |line, message: &str| PlyLoadError::Header {
+        line,
+        message: message.to_owned(),
+    };
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    let mut line_number = 0;
+    loop {
+        let Some(end) = bytes[offset..].iter().position(|&b| b == b'\n') else {
+            return Err(header_error(line_number + 1, "missing end_header"));
+        };
+        let line = String::from_utf8_lossy(&bytes[offset..offset + end]);
+        offset += end + 1;
+        line_number += 1;
+
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        if line_number == 1 {
+            if tokens != ["ply"] {
+                return Err(header_error(1, "not a PLY file"));
+            }
+            continue;
+        }
+        match tokens.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", format_name, _version] => {
+                format = Some(match *format_name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(header_error(line_number, "unknown format")),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| header_error(line_number, "invalid element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_type, item_type, name] => {
+                let property = match (Scalar::parse(count_type), Scalar::parse(item_type)) {
+                    (Some(count), Some(item)) => Property::List(count, item, name.to_string()),
+                    _ => return Err(header_error(line_number, "unknown property type")),
+                };
+                elements
+                    .last_mut()
+                    .ok_or_else(|| header_error(line_number, "property outside an element"))?
+                    .properties
+                    .push(property);
+            }
+            ["property", scalar, name] => {
+                let scalar = Scalar::parse(scalar)
+                    .ok_or_else(|| header_error(line_number, "unknown property type"))?;
+                elements
+                    .last_mut()
+                    .ok_or_else(|| header_error(line_number, "property outside an element"))?
+                    .properties
+                    .push(Property::Scalar(scalar, name.to_string()));
+            }
+            ["end_header"] => break,
+            _ => return Err(header_error(line_number, "unrecognized line")),
+        }
+    }
+
+    let format = format.ok_or_else(|| header_error(line_number, "missing format"))?;
+    Ok((format, elements, offset))
+}
+
+/// Reads the values of the body one at a time, whatever its encoding.
+struct BodyReader<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl BodyReader<'_> {
+    fn read(&mut self, scalar: Scalar) -> Option<f64> {
+        if self.format == Format::Ascii {
+            let rest = &self.bytes[self.offset..];
+            let start = rest.iter().position(|b| !b.is_ascii_whitespace())?;
+            let len = rest[start..]
+                .iter()
+                .position(|b| b.is_ascii_whitespace())
+                .unwrap_or(rest.len() - start);
+            self.offset += start + len;
+            return std::str::from_utf8(&rest[start..start + len])
+                .ok()?
+                .parse()
+                .ok();
+        }
+
+        let size = scalar.size();
+        let bytes = self.bytes.get(self.offset..self.offset + size)?;
+        self.offset += size;
+        let mut buf = [0; 8];
+        buf[..size].copy_from_slice(bytes);
+        if self.format == Format::BinaryBigEndian {
+            buf[..size].reverse();
+        }
+        Some(match scalar {
+            Scalar::I8 => buf[0] as i8 as f64,
+            Scalar::U8 => buf[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(buf),
+        })
+    }
+}
+
+fn parse_ply(bytes: &[u8]) -> Result<Mesh, PlyLoadError> {
+    let (format, elements, body) = parse_header(bytes)?;
+    let mut reader = BodyReader {
+        format,
+        bytes,
+        offset: body,
+    };
+
+    let mut mesh = Mesh::default();
+    let mut has_positions = false;
+    for element in &elements {
+        let data_error = |message: &str| PlyLoadError::Data {
+            element: element.name.clone(),
+            message: message.to_owned(),
+        };
+        let slot = |name: &str| element.properties.iter().position(|p| p.name() == name);
+        let slots = |names: &[&str]| names.iter().map(|n| slot(n)).collect::<Option<Vec<_>>>();
+
+        let is_vertex = element.name == "vertex";
+        let position = slots(&["x", "y", "z"]).filter(|_| is_vertex);
+        let normal = slots(&["nx", "ny", "nz"]).filter(|_| is_vertex);
+        let tex_coords = [["u", "v"], ["s", "t"], ["texture_u", "texture_v"]]
+            .iter()
+            .find_map(|names| slots(names))
+            .filter(|_| is_vertex);
+        let color = slots(&["red", "green", "blue"]).filter(|_| is_vertex);
+        let alpha = slot("alpha").filter(|_| is_vertex);
+        let face = (element.name == "face")
+            .then(|| slot("vertex_indices").or_else(|| slot("vertex_index")))
+            .flatten();
+
+        if is_vertex {
+            has_positions = position.is_some();
+            // the count comes from the header, only trust what the body can hold
+            let remaining = bytes.len() - reader.offset;
+            let fit = remaining / element.min_size(format).max(1);
+            mesh.vertices.reserve(element.count.min(fit));
+            mesh.attributes.normals = normal.is_some();
+            mesh.attributes.tex_coords = tex_coords.is_some();
+            mesh.attributes.colors = color.is_some();
+        }
+
+        // colours are stored as integers spanning the type's range
+        let unit_scaled = (0..element.properties.len())
+            .map(|i| Some(i) == alpha || color.as_ref().is_some_and(|c| c.contains(&i)))
+            .collect::<Vec<_>>();
+        let mut values = vec![0.; element.properties.len()];
+        let mut polygon = Vec::new();
+        for _ in 0..element.count {
+            for (i, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::Scalar(scalar, _) => {
+                        let value = reader
+                            .read(*scalar)
+                            .ok_or_else(|| data_error("truncated or invalid value"))?;
+                        values[i] = if unit_scaled[i] {
+                            value / scalar.unit_scale()
+                        } else {
+                            value
+                        };
+                    }
+                    Property::List(count, item, _) => {
+                        let n = reader
+                            .read(*count)
+                            .ok_or_else(|| data_error("truncated or invalid list"))?;
+                        let n = list_value(n)
+                            .ok_or_else(|| data_error("list length is not a whole number"))?;
+                        polygon.clear();
+                        for _ in 0..n {
+                            let index = reader
+                                .read(*item)
+                                .ok_or_else(|| data_error("truncated or invalid list"))?;
+                            polygon.push(
+                                list_value(index).ok_or_else(|| {
+                                    data_error("list index is not a whole number")
+                                })?,
+                            );
+                        }
+                        if Some(i) == face {
+                            for k in 2..polygon.len() {
+                                mesh.indices
+                                    .extend([polygon[0], polygon[k - 1], polygon[k]]);
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(position) = &position {
+                let mut vertex = Vertex::default();
+                gather(&mut vertex.position, position, &values);
+                if let Some(normal) = &normal {
+                    gather(&mut vertex.normal, normal, &values);
+                }
+                if let Some(tex_coords) = &tex_coords {
+                    gather(&mut vertex.tex_coords, tex_coords, &values);
+                }
+                if let Some(color) = &color {
+                    gather(&mut vertex.color, color, &values);
+                }
+                if let Some(alpha) = alpha {
+                    vertex.color[3] = values[alpha] as f32;
+                }
+                mesh.vertices.push(vertex);
+            }
+        }
+    }
+
+    if !has_positions {
+        return Err(PlyLoadError::MissingPositions);
+    }
+    let n = mesh.vertices.len() as u32;
+    if let Some(&i) = mesh.indices.iter().find(|&&i| i >= n) {
+        return Err(PlyLoadError::Data {
+            element: "face".to_owned(),
+            message: format!("index {} out of range for {} vertices", i, n),
+        });
+    }
+    if !mesh.attributes.normals {
+        mesh.generate_normals(NormalMode::default());
+    }
+    if mesh.attributes.tex_coords {
+        mesh.generate_tangents();
+    }
+    Ok(mesh)
+}
+
+/// A list length or index, `None` unless it is a non-negative integer that fits.
+fn list_value(value: f64) -> Option<u32> {
+    (value >= 0. && value.fract() == 0. && value <= u32::MAX as f64).then_some(value as u32)
+}
+
+fn gather(dst: &mut [f32], slots: &[usize], values: &[f64]) {
+    for (d, &i) in dst.iter_mut().zip(slots) {
+        *d = values[i] as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "element vertex 3\n\
+        property float x\nproperty float y\nproperty float z\n\
+        property uchar red\nproperty uchar green\nproperty uchar blue\n\
+        element face 1\nproperty list uchar int vertex_indices\nend_header\n";
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let mut bytes = format!("ply\nformat {} 1.0\n{}", format, HEADER).into_bytes();
+        let f32_bytes = |v: f32| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        for (p, c) in [
+            ([0., 0., 0.], [255, 0, 0]),
+            ([1., 0., 0.], [0, 255, 0]),
+            ([0., 1., 0.], [0, 0, 255]),
+        ] {
+            p.iter().for_each(|&v| bytes.extend(f32_bytes(v)));
+            bytes.extend(c);
+        }
+        bytes.push(3);
+        for i in [0_i32, 1, 2] {
+            bytes.extend(if big_endian {
+                i.to_be_bytes()
+            } else {
+                i.to_le_bytes()
+            });
+        }
+        bytes
+    }
+
+    fn check(mesh: &Mesh) {
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert!(mesh.attributes.colors && mesh.attributes.normals);
+        assert_eq!(mesh.vertices[1].position, [1., 0., 0.]);
+        assert_eq!(mesh.vertices[1].color, [0., 1., 0., 1.]);
+        assert_eq!(mesh.vertices[2].normal, [0., 0., 1.]);
+    }
+
+    #[test]
+    fn ascii() {
+        let src = format!(
+            "ply\nformat ascii 1.0\ncomment test\n{}0 0 0 255 0 0\n1 0 0 0 255 0\n0 1 0 0 0 255\n3 0 1 2\n",
+            HEADER
+        );
+        check(&parse_ply(src.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn binary_both_endians() {
+        check(&parse_ply(&binary(false)).unwrap());
+        check(&parse_ply(&binary(true)).unwrap());
+    }
+
+    #[test]
+    fn quads_are_triangulated() {
+        let src = "ply\nformat ascii 1.0\nelement vertex 4\n\
+            property float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar uint vertex_index\nend_header\n\
+            0 0 0\n1 0 0\n1 1 0\n0 1 0\n4 0 1 2 3\n";
+        assert_eq!(
+            parse_ply(src.as_bytes()).unwrap().indices,
+            [0, 1, 2, 0, 2, 3]
+        );
+    }
+
+    #[test]
+    fn truncated_body_is_an_error() {
+        let bytes = binary(false);
+        assert!(matches!(
+            parse_ply(&bytes[..bytes.len() - 2]),
+            Err(PlyLoadError::Data { .. })
+        ));
+        assert!(matches!(
+            parse_ply(b"ply\nformat ascii 1.0\nelement vertex 0\n"),
+            Err(PlyLoadError::Header { .. })
+        ));
+    }
+
+    #[test]
+    fn list_values_must_be_whole_numbers() {
+        for face in ["3 0 -1 2", "3 0 1.5 2", "-3 0 1 2", "2.5 0 1 2"] {
+            let src = "ply\nformat ascii 1.0\nelement vertex 3\n\
+                property float x\nproperty float y\nproperty float z\n\
+                element face 1\nproperty list float float vertex_indices\nend_header\n\
+                0 0 0\n1 0 0\n0 1 0\n"
+                .to_owned()
+                + face
+                + "\n";
+            assert!(
+                matches!(parse_ply(src.as_bytes()), Err(PlyLoadError::Data { .. })),
+                "{}",
+                face
+            );
+        }
+    }
+
+    #[test]
+    fn huge_counts_fail_without_allocating() {
+        for format in ["ascii", "binary_little_endian"] {
+            let header = format!(
+                "ply\nformat {} 1.0\nelement vertex 4000000000\n\
+                 property float x\nproperty float y\nproperty float z\nend_header\n",
+                format
+            );
+            let mut bytes = header.into_bytes();
+            bytes.extend_from_slice(b"0 0 0 1 0 0\n");
+            assert!(matches!(parse_ply(&bytes), Err(PlyLoadError::Data { .. })));
+        }
+    }
+}
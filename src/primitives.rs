@@ -0,0 +1,507 @@
+//! Procedural meshes centred at the origin, with outward facing counter
+//! clockwise triangles, normals, texture coordinates and tangents.
+//!
+//! Curved surfaces are built from a grid of `(columns + 1) * (rows + 1)`
+//! vertices: the first and last columns share positions but not texture
+//! coordinates so the texture wraps around without a seam.
+
+use std::{
+    collections::HashMap,
+    f32::consts::{FRAC_PI_2, PI, TAU},
+};
+
+use glam::{Vec2, Vec3};
+
+use crate::mesh::{Mesh, Vertex};
+
+mod teapot;
+
+pub use teapot::teapot;
+
+/// Axis aligned cube with edges `size` long, 4 vertices per face.
+pub fn cube(size: f32) -> Mesh {
+    let half = size / 2.;
+    let mut mesh = Mesh::default();
+    for axis in 0..3 {
+        for sign in [-1., 1.] {
+            let mut normal = [0.; 3];
+            normal[axis] = sign;
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            let base = mesh.vertices.len() as u32;
+            for (a, b) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+                let mut position = normal.map(|n| n * half);
+                position[u] = a * half;
+                position[v] = b * sign * half;
+                let tex_coords = [(a + 1.) / 2., (b + 1.) / 2.];
+                mesh.vertices
+                    .push(Vertex::new(position, normal).with_tex_coords(tex_coords));
+            }
+            mesh.indices
+                .extend([0, 1, 2, 0, 2, 3].into_iter().map(|i| base + i));
+        }
+    }
+    finish(mesh)
+}
+
+/// Sphere made of `rings` rows of `segments` quads, the rows touching the
+/// poles being triangles.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    assert!(
+        segments >= 3 && rings >= 2,
+        "a sphere needs 3 segments and 2 rings"
+    );
+    let mut mesh = Mesh::default();
+    for row in 0..=rings {
+        let theta = PI * row as f32 / rings as f32;
+        for column in 0..=segments {
+            let phi = TAU * column as f32 / segments as f32;
+            let normal = spherical(theta, phi);
+            let uv = Vec2::new(
+                column as f32 / segments as f32,
+                1. - row as f32 / rings as f32,
+            );
+            mesh.vertices.push(vertex(normal * radius, normal, uv));
+        }
+    }
+    grid(&mut mesh, 0, segments, rings);
+    remove_degenerate(&mut mesh);
+    finish(mesh)
+}
+
+/// Sphere made by splitting the faces of an icosahedron `subdivisions` times,
+/// `20 * 4^subdivisions` triangles of nearly equal size. Vertices along the
+/// texture seam and at the poles are duplicated.
+pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+    let t = (1. + 5_f32.sqrt()) / 2.;
+    let mut positions = [
+        [-1., t, 0.],
+        [1., t, 0.],
+        [-1., -t, 0.],
+        [1., -t, 0.],
+        [0., -1., t],
+        [0., 1., t],
+        [0., -1., -t],
+        [0., 1., -t],
+        [t, 0., -1.],
+        [t, 0., 1.],
+        [-t, 0., -1.],
+        [-t, 0., 1.],
+    ]
+    .map(|p| Vec3::from(p).normalize())
+    .to_vec();
+    let mut indices: Vec<u32> = vec![
+        0, 11, 5, 0, 5, 1, 0, 1, 7, 0, 7, 10, 0, 10, 11, 1, 5, 9, 5, 11, 4, 11, 10, 2, 10, 7, 6, 7,
+        1, 8, 3, 9, 4, 3, 4, 2, 3, 2, 6, 3, 6, 8, 3, 8, 9, 4, 9, 5, 2, 4, 11, 6, 2, 10, 8, 6, 7, 9,
+        8, 1,
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let p = (positions[a as usize] + positions[b as usize]).normalize();
+                positions.push(p);
+                positions.len() as u32 - 1
+            })
+        };
+        indices = indices
+            .chunks_exact(3)
+            .flat_map(|tri| {
+                let [a, b, c] = [tri[0], tri[1], tri[2]];
+                let [ab, bc, ca] = [midpoint(a, b), midpoint(b, c), midpoint(c, a)];
+                [a, ab, ca, b, bc, ab, c, ca, bc, ab, bc, ca]
+            })
+            .collect();
+    }
+
+    let mut mesh = Mesh {
+        vertices: positions
+            .iter()
+            .map(|&n| {
+                let u = n.z.atan2(n.x).rem_euclid(TAU) / TAU;
+                let v = 1. - n.y.clamp(-1., 1.).acos() / PI;
+                vertex(n * radius, n, Vec2::new(u, v))
+            })
+            .collect(),
+        indices,
+        ..Default::default()
+    };
+    split_seam(&mut mesh);
+    finish(mesh)
+}
+
+/// Cylinder along Y with closed caps, `height` tall.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> Mesh {
+    assert!(segments >= 3, "a cylinder needs 3 segments");
+    let mut mesh = Mesh::default();
+    for (row, y) in [height / 2., -height / 2.].into_iter().enumerate() {
+        for column in 0..=segments {
+            let phi = TAU * column as f32 / segments as f32;
+            let normal = Vec3::new(phi.cos(), 0., phi.sin());
+            let uv = Vec2::new(column as f32 / segments as f32, 1. - row as f32);
+            mesh.vertices
+                .push(vertex(normal * radius + Vec3::Y * y, normal, uv));
+        }
+    }
+    grid(&mut mesh, 0, segments, 1);
+    cap(&mut mesh, radius, height / 2., segments);
+    cap(&mut mesh, radius, -height / 2., segments);
+    finish(mesh)
+}
+
+/// Cone along Y with its apex at `height / 2` and a closed base.
+pub fn cone(radius: f32, height: f32, segments: u32) -> Mesh {
+    assert!(segments >= 3, "a cone needs 3 segments");
+    let mut mesh = Mesh::default();
+    let slope = Vec2::new(height, radius).normalize();
+    let normal = |phi: f32| Vec3::new(slope.x * phi.cos(), slope.y, slope.x * phi.sin());
+    // one apex per segment, its normal half way between the base corners
+    for column in 0..segments {
+        let u = (column as f32 + 0.5) / segments as f32;
+        let apex = Vec3::Y * height / 2.;
+        mesh.vertices
+            .push(vertex(apex, normal(TAU * u), Vec2::new(u, 1.)));
+    }
+    for column in 0..=segments {
+        let phi = TAU * column as f32 / segments as f32;
+        let position = Vec3::new(radius * phi.cos(), -height / 2., radius * phi.sin());
+        let uv = Vec2::new(column as f32 / segments as f32, 0.);
+        mesh.vertices.push(vertex(position, normal(phi), uv));
+    }
+    for column in 0..segments {
+        let ring = segments + column;
+        mesh.indices.extend([column, ring + 1, ring]);
+    }
+    cap(&mut mesh, radius, -height / 2., segments);
+    finish(mesh)
+}
+
+/// Torus around the Y axis, `major_radius` from the centre to the middle of
+/// the tube.
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> Mesh {
+    assert!(
+        major_segments >= 3 && minor_segments >= 3,
+        "a torus needs 3 segments each way"
+    );
+    let mut mesh = Mesh::default();
+    for row in 0..=minor_segments {
+        let theta = TAU * row as f32 / minor_segments as f32;
+        for column in 0..=major_segments {
+            let phi = TAU * column as f32 / major_segments as f32;
+            let around = Vec3::new(phi.cos(), 0., phi.sin());
+            let normal = around * theta.cos() - Vec3::Y * theta.sin();
+            let position = around * major_radius + normal * minor_radius;
+            let uv = Vec2::new(
+                column as f32 / major_segments as f32,
+                1. - row as f32 / minor_segments as f32,
+            );
+            mesh.vertices.push(vertex(position, normal, uv));
+        }
+    }
+    grid(&mut mesh, 0, major_segments, minor_segments);
+    finish(mesh)
+}
+
+/// Plane in XZ facing +Y, `size` wide along X and Z, split into
+/// `columns * rows` quads.
+pub fn plane(size: Vec2, columns: u32, rows: u32) -> Mesh {
+    assert!(columns >= 1 && rows >= 1, "a plane needs at least one quad");
+    let mut mesh = Mesh::default();
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let uv = Vec2::new(column as f32 / columns as f32, row as f32 / rows as f32);
+            let position = Vec3::new((uv.x - 0.5) * size.x, 0., (0.5 - uv.y) * size.y);
+            mesh.vertices.push(vertex(position, Vec3::Y, uv));
+        }
+    }
+    grid(&mut mesh, 0, columns, rows);
+    finish(mesh)
+}
+
+/// Capsule along Y: a cylinder `height` tall between two hemispheres of
+/// `rings` rows each, `height + 2 * radius` tall overall.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+    assert!(
+        segments >= 3 && rings >= 1,
+        "a capsule needs 3 segments and 1 ring"
+    );
+    let mut mesh = Mesh::default();
+    // texture coordinates follow the length along the surface
+    let arc = FRAC_PI_2 * radius;
+    let length = 2. * arc + height;
+    for (hemisphere, y) in [height / 2., -height / 2.].into_iter().enumerate() {
+        for ring in 0..=rings {
+            let theta = FRAC_PI_2 * (hemisphere as u32 * rings + ring) as f32 / rings as f32;
+            let distance = hemisphere as f32 * (arc + height) + arc * ring as f32 / rings as f32;
+            for column in 0..=segments {
+                let phi = TAU * column as f32 / segments as f32;
+                let normal = spherical(theta, phi);
+                let uv = Vec2::new(column as f32 / segments as f32, 1. - distance / length);
+                mesh.vertices
+                    .push(vertex(normal * radius + Vec3::Y * y, normal, uv));
+            }
+        }
+    }
+    grid(&mut mesh, 0, segments, 2 * rings + 1);
+    remove_degenerate(&mut mesh);
+    finish(mesh)
+}
+
+/// Unit vector at polar angle `theta` from +Y and azimuth `phi` from +X
+/// towards +Z. The poles are exact so their triangles collapse.
+fn spherical(theta: f32, phi: f32) -> Vec3 {
+    let (sin, cos) = theta.sin_cos();
+    if sin.abs() < 1e-6 {
+        return Vec3::Y * cos.signum();
+    }
+    Vec3::new(sin * phi.cos(), cos, sin * phi.sin())
+}
+
+fn vertex(position: Vec3, normal: Vec3, uv: Vec2) -> Vertex {
+    Vertex::new(position.to_array(), normal.to_array()).with_tex_coords(uv.to_array())
+}
+
+/// Two triangles per quad of a row major grid of `(columns + 1) * (rows + 1)`
+/// vertices starting at `base`. Faces point along `d(column) x d(row)`.
+fn grid(mesh: &mut Mesh, base: u32, columns: u32, rows: u32) {
+    let stride = columns + 1;
+    for row in 0..rows {
+        for column in 0..columns {
+            let a = base + row * stride + column;
+            let (b, c, d) = (a + 1, a + stride, a + stride + 1);
+            mesh.indices.extend([a, b, c, b, d, c]);
+        }
+    }
+}
+
+/// Flat disc at height `y` facing away from the origin.
+fn cap(mesh: &mut Mesh, radius: f32, y: f32, segments: u32) {
+    let normal = Vec3::Y * y.signum();
+    let center = mesh.vertices.len() as u32;
+    mesh.vertices
+        .push(vertex(Vec3::Y * y, normal, Vec2::splat(0.5)));
+    for column in 0..segments {
+        let phi = TAU * column as f32 / segments as f32;
+        let (sin, cos) = phi.sin_cos();
+        let uv = Vec2::new(0.5 + 0.5 * cos, 0.5 - 0.5 * sin * normal.y);
+        mesh.vertices
+            .push(vertex(Vec3::new(radius * cos, y, radius * sin), normal, uv));
+    }
+    for column in 0..segments {
+        let a = center + 1 + column;
+        let b = center + 1 + (column + 1) % segments;
+        if y > 0. {
+            mesh.indices.extend([center, b, a]);
+        } else {
+            mesh.indices.extend([center, a, b]);
+        }
+    }
+}
+
+/// Drop the triangles collapsed at the poles of a grid.
+fn remove_degenerate(mesh: &mut Mesh) {
+    let vertices = &mesh.vertices;
+    let mut kept = Vec::with_capacity(mesh.indices.len());
+    for tri in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| Vec3::from(vertices[tri[k] as usize].position));
+        if (b - a).cross(c - a).length_squared() > 0. {
+            kept.extend_from_slice(tri);
+        }
+    }
+    mesh.indices = kept;
+}
+
+/// Give the triangles crossing the `u = 0` meridian copies of their vertices
+/// with `u + 1`, and every triangle touching a pole its own pole vertex with
+/// the `u` of the triangle.
+fn split_seam(mesh: &mut Mesh) {
+    let is_pole = |v: &Vertex| v.normal[0] == 0. && v.normal[2] == 0.;
+    let mut wrapped = HashMap::new();
+    for t in 0..mesh.indices.len() / 3 {
+        let tri = [0, 1, 2].map(|k| mesh.indices[3 * t + k]);
+        let us = tri
+            .iter()
+            .map(|&i| &mesh.vertices[i as usize])
+            .filter(|v| !is_pole(v))
+            .map(|v| v.tex_coords[0])
+            .collect::<Vec<_>>();
+        let span =
+            us.iter().fold(0_f32, |m, &u| m.max(u)) - us.iter().fold(1_f32, |m, &u| m.min(u));
+        let wraps = span > 0.5;
+        for (k, &i) in tri.iter().enumerate() {
+            let v = mesh.vertices[i as usize];
+            if wraps && !is_pole(&v) && v.tex_coords[0] < 0.5 {
+                mesh.indices[3 * t + k] = *wrapped.entry(i).or_insert_with(|| {
+                    mesh.vertices
+                        .push(v.with_tex_coords([v.tex_coords[0] + 1., v.tex_coords[1]]));
+                    mesh.vertices.len() as u32 - 1
+                });
+            }
+        }
+        for (k, &i) in tri.iter().enumerate() {
+            let v = mesh.vertices[i as usize];
+            if is_pole(&v) {
+                let others = [(k + 1) % 3, (k + 2) % 3]
+                    .map(|o| mesh.vertices[mesh.indices[3 * t + o] as usize].tex_coords[0]);
+                let u = (others[0] + others[1]) / 2.;
+                mesh.vertices.push(v.with_tex_coords([u, v.tex_coords[1]]));
+                mesh.indices[3 * t + k] = mesh.vertices.len() as u32 - 1;
+            }
+        }
+    }
+    // the original pole vertices are no longer referenced
+    let mut used = vec![false; mesh.vertices.len()];
+    mesh.indices.iter().for_each(|&i| used[i as usize] = true);
+    let mut remap = Vec::with_capacity(mesh.vertices.len());
+    let mut vertices = Vec::with_capacity(mesh.vertices.len());
+    for (v, used) in mesh.vertices.iter().zip(used) {
+        remap.push(vertices.len() as u32);
+        if used {
+            vertices.push(*v);
+        }
+    }
+    mesh.indices
+        .iter_mut()
+        .for_each(|i| *i = remap[*i as usize]);
+    mesh.vertices = vertices;
+}
+
+fn finish(mut mesh: Mesh) -> Mesh {
+    mesh.attributes.normals = true;
+    mesh.attributes.tex_coords = true;
+    mesh.generate_tangents();
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every triangle faces the same way as its vertex normals, which point
+    /// away from the nearest point of `axis(position)`.
+    fn check(mesh: &Mesh, vertices: usize, triangles: usize, inside: impl Fn(Vec3) -> Vec3) {
+        assert_eq!(mesh.vertices.len(), vertices);
+        assert_eq!(mesh.indices.len(), 3 * triangles);
+        for v in &mesh.vertices {
+            let p = Vec3::from(v.position);
+            assert!(Vec3::from(v.normal).dot(p - inside(p)) > 0., "{:?}", v);
+        }
+        check_surface(mesh);
+    }
+
+    /// Unit normals with perpendicular tangents, agreeing with the winding of
+    /// every triangle using them.
+    fn check_surface(mesh: &Mesh) {
+        assert!(mesh.attributes.normals && mesh.attributes.tex_coords && mesh.attributes.tangents);
+        for v in &mesh.vertices {
+            let n = Vec3::from(v.normal);
+            assert!((n.length() - 1.).abs() < 1e-5, "{:?}", v);
+            assert!(
+                Vec3::from_slice(&v.tangent[..3]).dot(n).abs() < 1e-4,
+                "{:?}",
+                v
+            );
+        }
+        for tri in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| mesh.vertices[tri[k] as usize]);
+            let face = (Vec3::from(b.position) - Vec3::from(a.position))
+                .cross(Vec3::from(c.position) - Vec3::from(a.position));
+            assert!(face.length() > 0.);
+            for v in [a, b, c] {
+                assert!(face.dot(v.normal.into()) > 0., "{:?}", tri);
+            }
+        }
+    }
+
+    #[test]
+    fn cube_and_plane() {
+        check(&cube(2.), 24, 12, |_| Vec3::ZERO);
+        let plane = plane(Vec2::new(4., 2.), 4, 2);
+        check(&plane, 15, 16, |p| p - Vec3::Y);
+        assert_eq!(plane.vertices[0].position, [-2., 0., 1.]);
+    }
+
+    #[test]
+    fn spheres() {
+        let sphere = uv_sphere(2., 16, 8);
+        check(&sphere, 17 * 9, 2 * 16 * 8 - 2 * 16, |_| Vec3::ZERO);
+        assert!(sphere
+            .vertices
+            .iter()
+            .all(|v| (Vec3::from(v.position).length() - 2.).abs() < 1e-5));
+
+        for n in 0..3 {
+            let sphere = icosphere(1., n);
+            let triangles = 20 * 4_usize.pow(n);
+            assert_eq!(sphere.indices.len(), 3 * triangles);
+            check(&sphere, sphere.vertices.len(), triangles, |_| Vec3::ZERO);
+            // no triangle stretches back across the texture at the seam, the
+            // coarsest ones around the poles cover half of it
+            for tri in sphere.indices.chunks_exact(3) {
+                let us = [0, 1, 2].map(|k| sphere.vertices[tri[k] as usize].tex_coords[0]);
+                let span = us.iter().fold(0_f32, |m, &u| m.max(u))
+                    - us.iter().fold(2_f32, |m, &u| m.min(u));
+                assert!(span <= 0.5, "{:?}", us);
+            }
+        }
+    }
+
+    #[test]
+    fn cylinder_and_cone() {
+        check(&cylinder(1., 2., 12), 2 * 13 + 2 * 13, 4 * 12, |_| {
+            Vec3::ZERO
+        });
+        check(&cone(1., 2., 12), 12 + 13 + 13, 2 * 12, |_| {
+            Vec3::new(0., -0.5, 0.)
+        });
+    }
+
+    #[test]
+    fn torus_and_capsule() {
+        check(&torus(2., 0.5, 24, 12), 25 * 13, 2 * 24 * 12, |p| {
+            Vec3::new(p.x, 0., p.z).normalize() * 2.
+        });
+        let capsule = capsule(0.5, 1., 16, 4);
+        check(&capsule, 2 * 5 * 17, 2 * 16 * 9 - 2 * 16, |p| {
+            Vec3::new(0., p.y.clamp(-0.5, 0.5), 0.)
+        });
+        let aabb = capsule.aabb();
+        assert!(aabb.min.abs_diff_eq(Vec3::new(-0.5, -1., -0.5), 1e-5));
+        assert!(aabb.max.abs_diff_eq(Vec3::new(0.5, 1., 0.5), 1e-5));
+    }
+
+    #[test]
+    fn teapot_patches_face_outwards() {
+        for n in [8, 16] {
+            let teapot = teapot(2., n);
+            let grid = (n as usize + 1).pow(2);
+            assert_eq!(teapot.vertices.len(), 32 * grid);
+            // the first row of the 4 lid and 4 bottom patches collapses
+            assert_eq!(teapot.indices.len(), 3 * (64 * n * n - 8 * n) as usize);
+            check_surface(&teapot);
+            let aabb = teapot.aabb();
+            assert!((aabb.min.y + 1.).abs() < 1e-5 && (aabb.max.y - 1.).abs() < 1e-5);
+
+            // patch centres, the handle and spout being tubes around Z = 0 and
+            // the lid knob bulging from the axis
+            for (patch, centre) in teapot.vertices[n as usize / 2 * (n as usize + 2)..]
+                .iter()
+                .step_by(grid)
+                .enumerate()
+            {
+                let p = Vec3::from(centre.position);
+                let inside = match patch {
+                    12..=19 => p * Vec3::new(1., 1., 0.),
+                    20..=23 => p * Vec3::Y,
+                    _ => Vec3::ZERO,
+                };
+                assert!(Vec3::from(centre.normal).dot(p - inside) > 0., "{}", patch);
+            }
+        }
+    }
+}
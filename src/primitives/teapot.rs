@@ -0,0 +1,475 @@
+//! The Utah teapot, generated from Martin Newell's 32 bicubic Bezier patches.
+
+use glam::{Vec2, Vec3};
+
+use super::{finish, grid, remove_degenerate, vertex};
+use crate::mesh::Mesh;
+
+/// Height of the original model, from its bottom to the top of the lid knob.
+const NEWELL_HEIGHT: f32 = 3.15;
+
+/// Utah teapot `height` tall, standing along Y with the spout towards +X.
+/// Every patch is split into `tessellation * tessellation` quads with its own
+/// vertices, texture coordinates spanning the patch, and normals taken from
+/// the surface derivatives rather than from the triangles.
+pub fn teapot(height: f32, tessellation: u32) -> Mesh {
+    assert!(
+        tessellation >= 1,
+        "a teapot needs at least one quad per patch"
+    );
+    let scale = height / NEWELL_HEIGHT;
+    // the patches are Z up
+    let y_up = |p: Vec3| Vec3::new(p.x, p.z, -p.y);
+
+    let mut mesh = Mesh::default();
+    for patch in &PATCHES {
+        let control = patch.map(|i| Vec3::from(VERTICES[i as usize]));
+        let base = mesh.vertices.len() as u32;
+        for row in 0..=tessellation {
+            let u = row as f32 / tessellation as f32;
+            for column in 0..=tessellation {
+                let v = column as f32 / tessellation as f32;
+                let (position, du, dv) = evaluate(&control, u, v);
+                let mut normal = dv.cross(du);
+                if normal.length_squared() < 1e-12 {
+                    // the lid and bottom patches collapse their first row
+                    // onto the axis, where the surface is horizontal
+                    let (_, du, dv) = evaluate(&control, u.max(1e-3), v);
+                    normal = Vec3::Z * dv.cross(du).z.signum();
+                }
+                mesh.vertices.push(vertex(
+                    (y_up(position) - Vec3::Y * NEWELL_HEIGHT / 2.) * scale,
+                    y_up(normal.normalize()),
+                    Vec2::new(v, 1. - u),
+                ));
+            }
+        }
+        grid(&mut mesh, base, tessellation, tessellation);
+    }
+    remove_degenerate(&mut mesh);
+    finish(mesh)
+}
+
+/// Point of a bicubic patch and its derivatives along `u`, across the rows of
+/// `control`, and `v`, along them. De Casteljau's construction keeps the
+/// points of a collapsed row exactly equal.
+fn evaluate(control: &[Vec3; 16], u: f32, v: f32) -> (Vec3, Vec3, Vec3) {
+    let rows = [0, 4, 8, 12].map(|i| bezier([0, 1, 2, 3].map(|k| control[i + k]), v));
+    let (position, du) = bezier(rows.map(|r| r.0), u);
+    let (dv, _) = bezier(rows.map(|r| r.1), u);
+    (position, du, dv)
+}
+
+/// Point of a cubic Bezier curve at `t` and its derivative.
+fn bezier(p: [Vec3; 4], t: f32) -> (Vec3, Vec3) {
+    let lerp = |a: Vec3, b: Vec3| a + (b - a) * t;
+    let [a, b, c] = [lerp(p[0], p[1]), lerp(p[1], p[2]), lerp(p[2], p[3])];
+    let [d, e] = [lerp(a, b), lerp(b, c)];
+    (lerp(d, e), 3. * (e - d))
+}
+
+/// Control points of Newell's teapot, Z up. Four near duplicates of the lid
+/// knob's top point are kept from the original table but not referenced.
+const VERTICES: [[f32; 3]; 306] = [
+    [1.4, 0., 2.4],
+    [1.4, -0.784, 2.4],
+    [0.784, -1.4, 2.4],
+    [0., -1.4, 2.4],
+    [1.3375, 0., 2.53125],
+    [1.3375, -0.749, 2.53125],
+    [0.749, -1.3375, 2.53125],
+    [0., -1.3375, 2.53125],
+    [1.4375, 0., 2.53125],
+    [1.4375, -0.805, 2.53125],
+    [0.805, -1.4375, 2.53125],
+    [0., -1.4375, 2.53125],
+    [1.5, 0., 2.4],
+    [1.5, -0.84, 2.4],
+    [0.84, -1.5, 2.4],
+    [0., -1.5, 2.4],
+    [-0.784, -1.4, 2.4],
+    [-1.4, -0.784, 2.4],
+    [-1.4, 0., 2.4],
+    [-0.749, -1.3375, 2.53125],
+    [-1.3375, -0.749, 2.53125],
+    [-1.3375, 0., 2.53125],
+    [-0.805, -1.4375, 2.53125],
+    [-1.4375, -0.805, 2.53125],
+    [-1.4375, 0., 2.53125],
+    [-0.84, -1.5, 2.4],
+    [-1.5, -0.84, 2.4],
+    [-1.5, 0., 2.4],
+    [-1.4, 0.784, 2.4],
+    [-0.784, 1.4, 2.4],
+    [0., 1.4, 2.4],
+    [-1.3375, 0.749, 2.53125],
+    [-0.749, 1.3375, 2.53125],
+    [0., 1.3375, 2.53125],
+    [-1.4375, 0.805, 2.53125],
+    [-0.805, 1.4375, 2.53125],
+    [0., 1.4375, 2.53125],
+    [-1.5, 0.84, 2.4],
+    [-0.84, 1.5, 2.4],
+    [0., 1.5, 2.4],
+    [0.784, 1.4, 2.4],
+    [1.4, 0.784, 2.4],
+    [0.749, 1.3375, 2.53125],
+    [1.3375, 0.749, 2.53125],
+    [0.805, 1.4375, 2.53125],
+    [1.4375, 0.805, 2.53125],
+    [0.84, 1.5, 2.4],
+    [1.5, 0.84, 2.4],
+    [1.75, 0., 1.875],
+    [1.75, -0.98, 1.875],
+    [0.98, -1.75, 1.875],
+    [0., -1.75, 1.875],
+    [2., 0., 1.35],
+    [2., -1.12, 1.35],
+    [1.12, -2., 1.35],
+    [0., -2., 1.35],
+    [2., 0., 0.9],
+    [2., -1.12, 0.9],
+    [1.12, -2., 0.9],
+    [0., -2., 0.9],
+    [-0.98, -1.75, 1.875],
+    [-1.75, -0.98, 1.875],
+    [-1.75, 0., 1.875],
+    [-1.12, -2., 1.35],
+    [-2., -1.12, 1.35],
+    [-2., 0., 1.35],
+    [-1.12, -2., 0.9],
+    [-2., -1.12, 0.9],
+    [-2., 0., 0.9],
+    [-1.75, 0.98, 1.875],
+    [-0.98, 1.75, 1.875],
+    [0., 1.75, 1.875],
+    [-2., 1.12, 1.35],
+    [-1.12, 2., 1.35],
+    [0., 2., 1.35],
+    [-2., 1.12, 0.9],
+    [-1.12, 2., 0.9],
+    [0., 2., 0.9],
+    [0.98, 1.75, 1.875],
+    [1.75, 0.98, 1.875],
+    [1.12, 2., 1.35],
+    [2., 1.12, 1.35],
+    [1.12, 2., 0.9],
+    [2., 1.12, 0.9],
+    [2., 0., 0.45],
+    [2., -1.12, 0.45],
+    [1.12, -2., 0.45],
+    [0., -2., 0.45],
+    [1.5, 0., 0.225],
+    [1.5, -0.84, 0.225],
+    [0.84, -1.5, 0.225],
+    [0., -1.5, 0.225],
+    [1.5, 0., 0.15],
+    [1.5, -0.84, 0.15],
+    [0.84, -1.5, 0.15],
+    [0., -1.5, 0.15],
+    [-1.12, -2., 0.45],
+    [-2., -1.12, 0.45],
+    [-2., 0., 0.45],
+    [-0.84, -1.5, 0.225],
+    [-1.5, -0.84, 0.225],
+    [-1.5, 0., 0.225],
+    [-0.84, -1.5, 0.15],
+    [-1.5, -0.84, 0.15],
+    [-1.5, 0., 0.15],
+    [-2., 1.12, 0.45],
+    [-1.12, 2., 0.45],
+    [0., 2., 0.45],
+    [-1.5, 0.84, 0.225],
+    [-0.84, 1.5, 0.225],
+    [0., 1.5, 0.225],
+    [-1.5, 0.84, 0.15],
+    [-0.84, 1.5, 0.15],
+    [0., 1.5, 0.15],
+    [1.12, 2., 0.45],
+    [2., 1.12, 0.45],
+    [0.84, 1.5, 0.225],
+    [1.5, 0.84, 0.225],
+    [0.84, 1.5, 0.15],
+    [1.5, 0.84, 0.15],
+    [-1.6, 0., 2.025],
+    [-1.6, -0.3, 2.025],
+    [-1.5, -0.3, 2.25],
+    [-1.5, 0., 2.25],
+    [-2.3, 0., 2.025],
+    [-2.3, -0.3, 2.025],
+    [-2.5, -0.3, 2.25],
+    [-2.5, 0., 2.25],
+    [-2.7, 0., 2.025],
+    [-2.7, -0.3, 2.025],
+    [-3., -0.3, 2.25],
+    [-3., 0., 2.25],
+    [-2.7, 0., 1.8],
+    [-2.7, -0.3, 1.8],
+    [-3., -0.3, 1.8],
+    [-3., 0., 1.8],
+    [-1.5, 0.3, 2.25],
+    [-1.6, 0.3, 2.025],
+    [-2.5, 0.3, 2.25],
+    [-2.3, 0.3, 2.025],
+    [-3., 0.3, 2.25],
+    [-2.7, 0.3, 2.025],
+    [-3., 0.3, 1.8],
+    [-2.7, 0.3, 1.8],
+    [-2.7, 0., 1.575],
+    [-2.7, -0.3, 1.575],
+    [-3., -0.3, 1.35],
+    [-3., 0., 1.35],
+    [-2.5, 0., 1.125],
+    [-2.5, -0.3, 1.125],
+    [-2.65, -0.3, 0.9375],
+    [-2.65, 0., 0.9375],
+    [-2., -0.3, 0.9],
+    [-1.9, -0.3, 0.6],
+    [-1.9, 0., 0.6],
+    [-3., 0.3, 1.35],
+    [-2.7, 0.3, 1.575],
+    [-2.65, 0.3, 0.9375],
+    [-2.5, 0.3, 1.125],
+    [-1.9, 0.3, 0.6],
+    [-2., 0.3, 0.9],
+    [1.7, 0., 1.425],
+    [1.7, -0.66, 1.425],
+    [1.7, -0.66, 0.6],
+    [1.7, 0., 0.6],
+    [2.6, 0., 1.425],
+    [2.6, -0.66, 1.425],
+    [3.1, -0.66, 0.825],
+    [3.1, 0., 0.825],
+    [2.3, 0., 2.1],
+    [2.3, -0.25, 2.1],
+    [2.4, -0.25, 2.025],
+    [2.4, 0., 2.025],
+    [2.7, 0., 2.4],
+    [2.7, -0.25, 2.4],
+    [3.3, -0.25, 2.4],
+    [3.3, 0., 2.4],
+    [1.7, 0.66, 0.6],
+    [1.7, 0.66, 1.425],
+    [3.1, 0.66, 0.825],
+    [2.6, 0.66, 1.425],
+    [2.4, 0.25, 2.025],
+    [2.3, 0.25, 2.1],
+    [3.3, 0.25, 2.4],
+    [2.7, 0.25, 2.4],
+    [2.8, 0., 2.475],
+    [2.8, -0.25, 2.475],
+    [3.525, -0.25, 2.49375],
+    [3.525, 0., 2.49375],
+    [2.9, 0., 2.475],
+    [2.9, -0.15, 2.475],
+    [3.45, -0.15, 2.5125],
+    [3.45, 0., 2.5125],
+    [2.8, 0., 2.4],
+    [2.8, -0.15, 2.4],
+    [3.2, -0.15, 2.4],
+    [3.2, 0., 2.4],
+    [3.525, 0.25, 2.49375],
+    [2.8, 0.25, 2.475],
+    [3.45, 0.15, 2.5125],
+    [2.9, 0.15, 2.475],
+    [3.2, 0.15, 2.4],
+    [2.8, 0.15, 2.4],
+    [0., 0., 3.15],
+    [0., -0.002, 3.15],
+    [0.002, 0., 3.15],
+    [0.8, 0., 3.15],
+    [0.8, -0.45, 3.15],
+    [0.45, -0.8, 3.15],
+    [0., -0.8, 3.15],
+    [0., 0., 2.85],
+    [0.2, 0., 2.7],
+    [0.2, -0.112, 2.7],
+    [0.112, -0.2, 2.7],
+    [0., -0.2, 2.7],
+    [-0.002, 0., 3.15],
+    [-0.45, -0.8, 3.15],
+    [-0.8, -0.45, 3.15],
+    [-0.8, 0., 3.15],
+    [-0.112, -0.2, 2.7],
+    [-0.2, -0.112, 2.7],
+    [-0.2, 0., 2.7],
+    [0., 0.002, 3.15],
+    [-0.8, 0.45, 3.15],
+    [-0.45, 0.8, 3.15],
+    [0., 0.8, 3.15],
+    [-0.2, 0.112, 2.7],
+    [-0.112, 0.2, 2.7],
+    [0., 0.2, 2.7],
+    [0.45, 0.8, 3.15],
+    [0.8, 0.45, 3.15],
+    [0.112, 0.2, 2.7],
+    [0.2, 0.112, 2.7],
+    [0.4, 0., 2.55],
+    [0.4, -0.224, 2.55],
+    [0.224, -0.4, 2.55],
+    [0., -0.4, 2.55],
+    [1.3, 0., 2.55],
+    [1.3, -0.728, 2.55],
+    [0.728, -1.3, 2.55],
+    [0., -1.3, 2.55],
+    [1.3, 0., 2.4],
+    [1.3, -0.728, 2.4],
+    [0.728, -1.3, 2.4],
+    [0., -1.3, 2.4],
+    [-0.224, -0.4, 2.55],
+    [-0.4, -0.224, 2.55],
+    [-0.4, 0., 2.55],
+    [-0.728, -1.3, 2.55],
+    [-1.3, -0.728, 2.55],
+    [-1.3, 0., 2.55],
+    [-0.728, -1.3, 2.4],
+    [-1.3, -0.728, 2.4],
+    [-1.3, 0., 2.4],
+    [-0.4, 0.224, 2.55],
+    [-0.224, 0.4, 2.55],
+    [0., 0.4, 2.55],
+    [-1.3, 0.728, 2.55],
+    [-0.728, 1.3, 2.55],
+    [0., 1.3, 2.55],
+    [-1.3, 0.728, 2.4],
+    [-0.728, 1.3, 2.4],
+    [0., 1.3, 2.4],
+    [0.224, 0.4, 2.55],
+    [0.4, 0.224, 2.55],
+    [0.728, 1.3, 2.55],
+    [1.3, 0.728, 2.55],
+    [0.728, 1.3, 2.4],
+    [1.3, 0.728, 2.4],
+    [0., 0., 0.],
+    [1.5, 0., 0.15],
+    [1.5, 0.84, 0.15],
+    [0.84, 1.5, 0.15],
+    [0., 1.5, 0.15],
+    [1.5, 0., 0.075],
+    [1.5, 0.84, 0.075],
+    [0.84, 1.5, 0.075],
+    [0., 1.5, 0.075],
+    [1.425, 0., 0.],
+    [1.425, 0.798, 0.],
+    [0.798, 1.425, 0.],
+    [0., 1.425, 0.],
+    [-0.84, 1.5, 0.15],
+    [-1.5, 0.84, 0.15],
+    [-1.5, 0., 0.15],
+    [-0.84, 1.5, 0.075],
+    [-1.5, 0.84, 0.075],
+    [-1.5, 0., 0.075],
+    [-0.798, 1.425, 0.],
+    [-1.425, 0.798, 0.],
+    [-1.425, 0., 0.],
+    [-1.5, -0.84, 0.15],
+    [-0.84, -1.5, 0.15],
+    [0., -1.5, 0.15],
+    [-1.5, -0.84, 0.075],
+    [-0.84, -1.5, 0.075],
+    [0., -1.5, 0.075],
+    [-1.425, -0.798, 0.],
+    [-0.798, -1.425, 0.],
+    [0., -1.425, 0.],
+    [0.84, -1.5, 0.15],
+    [1.5, -0.84, 0.15],
+    [0.84, -1.5, 0.075],
+    [1.5, -0.84, 0.075],
+    [0.798, -1.425, 0.],
+    [1.425, -0.798, 0.],
+];
+
+/// Indices into [`VERTICES`] of the 4x4 control points of each patch, row by
+/// row: rim, body, handle, spout, lid and bottom.
+const PATCHES: [[u16; 16]; 32] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [3, 16, 17, 18, 7, 19, 20, 21, 11, 22, 23, 24, 15, 25, 26, 27],
+    [
+        18, 28, 29, 30, 21, 31, 32, 33, 24, 34, 35, 36, 27, 37, 38, 39,
+    ],
+    [30, 40, 41, 0, 33, 42, 43, 4, 36, 44, 45, 8, 39, 46, 47, 12],
+    [
+        12, 13, 14, 15, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59,
+    ],
+    [
+        15, 25, 26, 27, 51, 60, 61, 62, 55, 63, 64, 65, 59, 66, 67, 68,
+    ],
+    [
+        27, 37, 38, 39, 62, 69, 70, 71, 65, 72, 73, 74, 68, 75, 76, 77,
+    ],
+    [
+        39, 46, 47, 12, 71, 78, 79, 48, 74, 80, 81, 52, 77, 82, 83, 56,
+    ],
+    [
+        56, 57, 58, 59, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95,
+    ],
+    [
+        59, 66, 67, 68, 87, 96, 97, 98, 91, 99, 100, 101, 95, 102, 103, 104,
+    ],
+    [
+        68, 75, 76, 77, 98, 105, 106, 107, 101, 108, 109, 110, 104, 111, 112, 113,
+    ],
+    [
+        77, 82, 83, 56, 107, 114, 115, 84, 110, 116, 117, 88, 113, 118, 119, 92,
+    ],
+    [
+        120, 121, 122, 123, 124, 125, 126, 127, 128, 129, 130, 131, 132, 133, 134, 135,
+    ],
+    [
+        123, 136, 137, 120, 127, 138, 139, 124, 131, 140, 141, 128, 135, 142, 143, 132,
+    ],
+    [
+        132, 133, 134, 135, 144, 145, 146, 147, 148, 149, 150, 151, 68, 152, 153, 154,
+    ],
+    [
+        135, 142, 143, 132, 147, 155, 156, 144, 151, 157, 158, 148, 154, 159, 160, 68,
+    ],
+    [
+        161, 162, 163, 164, 165, 166, 167, 168, 169, 170, 171, 172, 173, 174, 175, 176,
+    ],
+    [
+        164, 177, 178, 161, 168, 179, 180, 165, 172, 181, 182, 169, 176, 183, 184, 173,
+    ],
+    [
+        173, 174, 175, 176, 185, 186, 187, 188, 189, 190, 191, 192, 193, 194, 195, 196,
+    ],
+    [
+        176, 183, 184, 173, 188, 197, 198, 185, 192, 199, 200, 189, 196, 201, 202, 193,
+    ],
+    [
+        203, 203, 203, 203, 206, 207, 208, 209, 210, 210, 210, 210, 211, 212, 213, 214,
+    ],
+    [
+        203, 203, 203, 203, 209, 216, 217, 218, 210, 210, 210, 210, 214, 219, 220, 221,
+    ],
+    [
+        203, 203, 203, 203, 218, 223, 224, 225, 210, 210, 210, 210, 221, 226, 227, 228,
+    ],
+    [
+        203, 203, 203, 203, 225, 229, 230, 206, 210, 210, 210, 210, 228, 231, 232, 211,
+    ],
+    [
+        211, 212, 213, 214, 233, 234, 235, 236, 237, 238, 239, 240, 241, 242, 243, 244,
+    ],
+    [
+        214, 219, 220, 221, 236, 245, 246, 247, 240, 248, 249, 250, 244, 251, 252, 253,
+    ],
+    [
+        221, 226, 227, 228, 247, 254, 255, 256, 250, 257, 258, 259, 253, 260, 261, 262,
+    ],
+    [
+        228, 231, 232, 211, 256, 263, 264, 233, 259, 265, 266, 237, 262, 267, 268, 241,
+    ],
+    [
+        269, 269, 269, 269, 278, 279, 280, 281, 274, 275, 276, 277, 270, 271, 272, 273,
+    ],
+    [
+        269, 269, 269, 269, 281, 288, 289, 290, 277, 285, 286, 287, 273, 282, 283, 284,
+    ],
+    [
+        269, 269, 269, 269, 290, 297, 298, 299, 287, 294, 295, 296, 284, 291, 292, 293,
+    ],
+    [
+        269, 269, 269, 269, 299, 304, 305, 278, 296, 302, 303, 274, 293, 300, 301, 270,
+    ],
+];
@@ -0,0 +1,246 @@
+use std::{
+    error::Error,
+    fmt::{self, Debug},
+    fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use glam::Vec3;
+
+use crate::mesh::{LoadStats, Mesh, Vertex};
+
+#[derive(Debug)]
+pub enum StlLoadError {
+    /// The STL file could not be opened or read.
+    Io { path: PathBuf, source: io::Error },
+    /// The binary triangle count does not match the file size.
+    Truncated { expected: usize, found: usize },
+    /// An ASCII line does not parse, `line` counts from 1.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for StlLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlLoadError::Io { path, source } => {
+                write!(f, "failed to read {}: {}", path.display(), source)
+            }
+            StlLoadError::Truncated { expected, found } => write!(
+                f,
+                "binary STL should be {} bytes long, found {}",
+                expected, found
+            ),
+            StlLoadError::Parse { line, message } => {
+                write!(f, "invalid STL at line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl Error for StlLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StlLoadError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Load an STL file, panicking on any error. Meant for the examples, use
+/// [`try_load_stl`] otherwise.
+pub fn load_stl<P>(path: P) -> Mesh
+where
+    P: AsRef<Path> + Debug,
+{
+    try_load_stl(&path).unwrap_or_else(|e| panic!("Failed to load STL file {:?}: {}", path, e))
+}
+
+/// Load an ASCII or binary STL file. STL has no shared vertices, every facet
+/// gets three vertices with the facet normal, recomputed from the winding
+/// when the file leaves it zero.
+pub fn try_load_stl<P>(path: P) -> Result<Mesh, StlLoadError>
+where
+    P: AsRef<Path>,
+{
+    let start = Instant::now();
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| StlLoadError::Io {
+        path: path.to_owned(),
+        source,
+    })?;
+    let mesh = parse_stl(&bytes)?;
+    log::info!(
+        "Loaded {}: {}",
+        path.display(),
+        LoadStats::of_mesh(&mesh, start.elapsed())
+    );
+    Ok(mesh)
+}
+
+fn parse_stl(bytes: &[u8]) -> Result<Mesh, StlLoadError> {
+    if bytes.len() < 84 {
+        return parse_ascii(&String::from_utf8_lossy(bytes));
+    }
+    // binary files may start with "solid" too, so trust the size first
+    let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+    let expected = count.saturating_mul(50).saturating_add(84);
+    if bytes.len() == expected {
+        return Ok(parse_binary(&bytes[84..], count));
+    }
+    // some exporters pad binary files past the last triangle
+    let ascii = if bytes.starts_with(b"solid") {
+        parse_ascii(&String::from_utf8_lossy(bytes))
+    } else {
+        Err(StlLoadError::Truncated {
+            expected,
+            found: bytes.len(),
+        })
+    };
+    match ascii {
+        Err(_) if bytes.len() > expected => Ok(parse_binary(&bytes[84..], count)),
+        ascii => ascii,
+    }
+}
+
+fn parse_binary(body: &[u8], count: usize) -> Mesh {
+    let mut mesh = Mesh::default();
+    let f32_at = |chunk: &[u8], i: usize| {
+        f32::from_le_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]])
+    };
+    for chunk in body.chunks_exact(50).take(count) {
+        let v = [0, 1, 2, 3].map(|k| {
+            Vec3::new(
+                f32_at(chunk, 12 * k),
+                f32_at(chunk, 12 * k + 4),
+                f32_at(chunk, 12 * k + 8),
+            )
+        });
+        push_facet(&mut mesh, v[0], [v[1], v[2], v[3]]);
+    }
+    finish(mesh)
+}
+
+fn parse_ascii(text: &str) -> Result<Mesh, StlLoadError> {
+    let mut mesh = Mesh::default();
+    let mut normal = Vec3::ZERO;
+    let mut corners = Vec::with_capacity(3);
+    let mut ended = false;
+    let mut lines = 0;
+    for (i, line) in text.lines().enumerate() {
+        lines = i + 1;
+        let parse_error = |message: &str| StlLoadError::Parse {
+            line: i + 1,
+            message: message.to_owned(),
+        };
+        let vec3 = |values: &[&str]| -> Result<Vec3, StlLoadError> {
+            let v = values
+                .iter()
+                .map(|v| v.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| parse_error("invalid number"))?;
+            Ok(Vec3::from_slice(&v))
+        };
+
+        match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["facet", "normal", values @ ..] if values.len() == 3 => {
+                normal = vec3(values)?;
+                corners.clear();
+            }
+            ["vertex", values @ ..] if values.len() == 3 => corners.push(vec3(values)?),
+            ["endfacet"] => {
+                if corners.len() != 3 {
+                    return Err(parse_error("facet does not have 3 vertices"));
+                }
+                push_facet(&mut mesh, normal, [corners[0], corners[1], corners[2]]);
+            }
+            ["endsolid", ..] => ended = true,
+            [] | ["solid", ..] | ["outer", "loop"] | ["endloop"] => {}
+            _ => return Err(parse_error("unrecognized line")),
+        }
+    }
+    // also catches binary files whose header starts with "solid"
+    if !ended {
+        return Err(StlLoadError::Parse {
+            line: lines,
+            message: "missing endsolid".to_owned(),
+        });
+    }
+    Ok(finish(mesh))
+}
+
+fn push_facet(mesh: &mut Mesh, normal: Vec3, p: [Vec3; 3]) {
+    let normal = if normal.length_squared() > 0. {
+        normal.normalize()
+    } else {
+        (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero()
+    };
+    let base = mesh.vertices.len() as u32;
+    for p in p {
+        mesh.vertices
+            .push(Vertex::new(p.to_array(), normal.to_array()));
+    }
+    mesh.indices.extend([base, base + 1, base + 2]);
+}
+
+fn finish(mut mesh: Mesh) -> Mesh {
+    mesh.attributes.normals = true;
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii() {
+        let src = "solid test\n\
+            facet normal 0 0 0\n outer loop\n\
+            vertex 0 0 0\n vertex 1 0 0\n vertex 0 1 0\n\
+            endloop\nendfacet\nendsolid test\n";
+        let mesh = parse_stl(src.as_bytes()).unwrap();
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert_eq!(mesh.vertices[1].position, [1., 0., 0.]);
+        assert_eq!(mesh.vertices[0].normal, [0., 0., 1.]);
+
+        assert!(matches!(
+            parse_stl(b"solid test\nfacet normal 0 0 1\nvertex 0 x 0\n"),
+            Err(StlLoadError::Parse { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn binary_with_solid_header() {
+        let mut bytes = b"solid but actually binary".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend(2_u32.to_le_bytes());
+        for z in [0., 1.] {
+            for v in [[0., 0., 2.], [0., 0., z], [1., 0., z], [0., 1., z]] {
+                v.iter().for_each(|c: &f32| bytes.extend(c.to_le_bytes()));
+            }
+            bytes.extend([0, 0]);
+        }
+        let mesh = parse_stl(&bytes).unwrap();
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.vertices[5].position, [0., 1., 1.]);
+        assert_eq!(mesh.vertices[0].normal, [0., 0., 1.]);
+
+        bytes[80] = 3;
+        assert!(matches!(parse_stl(&bytes), Err(StlLoadError::Parse { .. })));
+
+        // trailing padding after the last triangle is ignored
+        bytes[80] = 2;
+        bytes.extend([0; 16]);
+        assert_eq!(parse_stl(&bytes).unwrap().vertices.len(), 6);
+        bytes[..5].copy_from_slice(b"model");
+        assert_eq!(parse_stl(&bytes).unwrap().vertices.len(), 6);
+        bytes.truncate(100);
+        assert!(matches!(
+            parse_stl(&bytes),
+            Err(StlLoadError::Truncated {
+                expected: 184,
+                found: 100
+            })
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+use std::{error::Error, fmt, ops::Range};
+
+use glium::{
+    backend::Facade,
+    index::{self, IndexType, IndicesSource, PrimitiveType},
+    vertex, VertexBuffer,
+};
+
+use crate::mesh::{Mesh, Model, Vertex};
+
+/// Number of vertices `u16` indices can address.
+pub const U16_VERTEX_LIMIT: usize = u16::MAX as usize + 1;
+
+/// An index buffer holding `u16` indices when they all fit and `u32`
+/// otherwise. A reference to it can be given to `Frame::draw` directly.
+#[derive(Debug)]
+pub enum IndexBuffer {
+    U16(index::IndexBuffer<u16>),
+    U32(index::IndexBuffer<u32>),
+}
+
+impl IndexBuffer {
+    pub fn new<F>(
+        facade: &F,
+        primitive: PrimitiveType,
+        indices: &[u32],
+    ) -> Result<Self, index::BufferCreationError>
+    where
+        F: Facade + ?Sized,
+    {
+        if indices.iter().all(|&i| (i as usize) < U16_VERTEX_LIMIT) {
+            let indices = indices.iter().map(|&i| i as u16).collect::<Vec<_>>();
+            index::IndexBuffer::new(facade, primitive, &indices).map(IndexBuffer::U16)
+        } else {
+            index::IndexBuffer::new(facade, primitive, indices).map(IndexBuffer::U32)
+        }
+    }
+
+    pub fn index_type(&self) -> IndexType {
+        match self {
+            IndexBuffer::U16(_) => IndexType::U16,
+            IndexBuffer::U32(_) => IndexType::U32,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            IndexBuffer::U16(b) => b.len(),
+            IndexBuffer::U32(b) => b.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The indices in `range`, `None` if it is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> Option<IndicesSource<'_>> {
+        match self {
+            IndexBuffer::U16(b) => b.slice(range).map(Into::into),
+            IndexBuffer::U32(b) => b.slice(range).map(Into::into),
+        }
+    }
+}
+
+impl<'a> From<&'a IndexBuffer> for IndicesSource<'a> {
+    fn from(buffer: &'a IndexBuffer) -> Self {
+        match buffer {
+            IndexBuffer::U16(b) => b.into(),
+            IndexBuffer::U32(b) => b.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum UploadError {
+    Vertex(vertex::BufferCreationError),
+    Index(index::BufferCreationError),
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::Vertex(e) => write!(f, "failed to create the vertex buffer: {}", e),
+            UploadError::Index(e) => write!(f, "failed to create the index buffer: {}", e),
+        }
+    }
+}
+
+impl Error for UploadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UploadError::Vertex(e) => Some(e),
+            UploadError::Index(e) => Some(e),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadOptions {
+    /// Meshes with more vertices are split into several buffers. Lowered to
+    /// [`U16_VERTEX_LIMIT`] when the context has no `u32` indices.
+    pub max_vertices: usize,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        UploadOptions {
+            max_vertices: u32::MAX as usize,
+        }
+    }
+}
+
+/// Vertex and index buffers holding a mesh, or a part of it when it was split.
+#[derive(Debug)]
+pub struct MeshBuffers {
+    pub vertices: VertexBuffer<Vertex>,
+    pub indices: IndexBuffer,
+    /// Index of a submesh and the range of `indices` drawing it, for every
+    /// submesh with triangles in this part.
+    pub submeshes: Vec<(usize, Range<usize>)>,
+}
+
+/// Upload `mesh` as a triangle list, see [`upload_model`].
+pub fn upload_mesh<F>(
+    facade: &F,
+    mesh: &Mesh,
+    options: &UploadOptions,
+) -> Result<Vec<MeshBuffers>, UploadError>
+where
+    F: Facade + ?Sized,
+{
+    let whole = 0..mesh.indices.len() as u32;
+    upload(facade, mesh, std::slice::from_ref(&whole), options)
+}
+
+/// Upload `model` as a triangle list. One set of buffers is returned unless
+/// the mesh has more vertices than [`UploadOptions::max_vertices`], each
+/// picking `u16` indices when its vertex count allows.
+pub fn upload_model<F>(
+    facade: &F,
+    model: &Model,
+    options: &UploadOptions,
+) -> Result<Vec<MeshBuffers>, UploadError>
+where
+    F: Facade + ?Sized,
+{
+    let ranges = model
+        .submeshes
+        .iter()
+        .map(|s| s.indices.clone())
+        .collect::<Vec<_>>();
+    upload(facade, &model.mesh, &ranges, options)
+}
+
+fn upload<F>(
+    facade: &F,
+    mesh: &Mesh,
+    ranges: &[Range<u32>],
+    options: &UploadOptions,
+) -> Result<Vec<MeshBuffers>, UploadError>
+where
+    F: Facade + ?Sized,
+{
+    let mut max_vertices = options.max_vertices;
+    if !IndexType::U32.is_supported(facade) {
+        max_vertices = max_vertices.min(U16_VERTEX_LIMIT);
+    }
+    let buffers = |vertices: &[Vertex], indices: &[u32], submeshes| {
+        Ok(MeshBuffers {
+            vertices: VertexBuffer::new(facade, vertices).map_err(UploadError::Vertex)?,
+            indices: IndexBuffer::new(facade, PrimitiveType::TrianglesList, indices)
+                .map_err(UploadError::Index)?,
+            submeshes,
+        })
+    };
+
+    if mesh.vertices.len() <= max_vertices {
+        let submeshes = ranges
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (i, r.start as usize..r.end as usize))
+            .collect();
+        return Ok(vec![buffers(&mesh.vertices, &mesh.indices, submeshes)?]);
+    }
+    split(&mesh.indices, ranges, max_vertices)
+        .into_iter()
+        .map(|part| {
+            let vertices = part
+                .vertex_map
+                .iter()
+                .map(|&i| mesh.vertices[i as usize])
+                .collect::<Vec<_>>();
+            buffers(&vertices, &part.indices, part.submeshes)
+        })
+        .collect()
+}
+
+/// A piece of a split mesh, indexing its own vertices.
+#[derive(Debug, Default)]
+struct Part {
+    /// For every local vertex, its index in the source mesh.
+    vertex_map: Vec<u32>,
+    indices: Vec<u32>,
+    submeshes: Vec<(usize, Range<usize>)>,
+}
+
+/// Cut the triangles of `ranges` into parts of at most `max_vertices`
+/// vertices, keeping the triangle order.
+fn split(indices: &[u32], ranges: &[Range<u32>], max_vertices: usize) -> Vec<Part> {
+    assert!(max_vertices >= 3, "a part must hold a triangle");
+    let mut parts = vec![Part::default()];
+    // local index of each source vertex in the current part
+    let mut local = vec![u32::MAX; indices.iter().max().map_or(0, |&i| i as usize + 1)];
+
+    for (submesh, range) in ranges.iter().enumerate() {
+        let range = range.start as usize..range.end as usize;
+        for tri in indices[range].chunks_exact(3) {
+            let part = parts.last_mut().unwrap();
+            let new = (0..3)
+                .filter(|&k| local[tri[k] as usize] == u32::MAX && !tri[..k].contains(&tri[k]))
+                .count();
+            if part.vertex_map.len() + new > max_vertices {
+                part.vertex_map
+                    .iter()
+                    .for_each(|&i| local[i as usize] = u32::MAX);
+                parts.push(Part::default());
+            }
+
+            let part = parts.last_mut().unwrap();
+            let start = part.indices.len();
+            for &i in tri {
+                if local[i as usize] == u32::MAX {
+                    local[i as usize] = part.vertex_map.len() as u32;
+                    part.vertex_map.push(i);
+                }
+                part.indices.push(local[i as usize]);
+            }
+            match part.submeshes.last_mut() {
+                Some((s, r)) if *s == submesh => r.end = part.indices.len(),
+                _ => part.submeshes.push((submesh, start..part.indices.len())),
+            }
+        }
+    }
+    parts.retain(|p| !p.indices.is_empty());
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_parts_respect_the_limit_and_keep_triangles() {
+        // 20x20 quads, 441 vertices
+        let n = 20;
+        let mut indices = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                indices.extend([i, i + 1, i + n + 2, i, i + n + 2, i + n + 1]);
+            }
+        }
+        let ranges = [0..600, 600..indices.len() as u32];
+        let parts = split(&indices, &ranges, 100);
+        assert!(parts.len() >= 5);
+
+        let mut triangles = Vec::new();
+        let mut submeshes = vec![0; ranges.len()];
+        for part in &parts {
+            assert!(part.vertex_map.len() <= 100);
+            triangles.extend(part.indices.iter().map(|&i| part.vertex_map[i as usize]));
+            for (s, r) in &part.submeshes {
+                submeshes[*s] += r.len();
+            }
+        }
+        assert_eq!(triangles, indices);
+        assert_eq!(submeshes, [600, indices.len() - 600]);
+    }
+
+    #[test]
+    fn submeshes_within_a_part_are_separate() {
+        let indices = [0, 1, 2, 2, 1, 3, 3, 1, 4];
+        let parts = split(&indices, &[0..3, 3..9], 5);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].submeshes, [(0, 0..3), (1, 3..9)]);
+
+        let parts = split(&indices, &[0..3, 3..9], 4);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].vertex_map, [3, 1, 4]);
+        assert_eq!(parts[1].submeshes, [(1, 0..3)]);
+    }
+}